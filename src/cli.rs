@@ -0,0 +1,111 @@
+/// Summary of the accepted command-line arguments.
+pub const USAGE: &str = "Usage: elmlog [--theme <name>] \
+    [capture <text> | view [--sep <separator>]]\n\
+    --theme may be given anywhere before the capture text.";
+
+/// The action selected by the command-line arguments.
+pub enum CliCommand {
+    Tui,
+    Capture(String),
+    View { sep: Option<String> },
+}
+
+/// The parsed command-line arguments.
+pub struct CliArgs {
+    pub theme: Option<String>,
+    pub command: CliCommand,
+}
+
+/// Parse the command-line arguments, returning None if they are invalid.
+pub fn parse_args() -> Option<CliArgs> {
+    parse(std::env::args().skip(1))
+}
+
+// Parse the arguments after the program name. `--theme` may appear once
+// anywhere, except in the capture text, which is kept verbatim.
+fn parse(mut args: impl Iterator<Item = String>) -> Option<CliArgs> {
+    let mut theme = None;
+    let mut rest = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "--theme" && theme.is_none() {
+            theme = Some(args.next().filter(|name| !name.is_empty())?);
+        } else if arg == "capture" && rest.is_empty() {
+            rest.push(arg);
+            rest.extend(args.by_ref());
+        } else {
+            rest.push(arg);
+        }
+    }
+    let command = parse_command(rest.into_iter())?;
+    Some(CliArgs { theme, command })
+}
+
+// Parse the subcommand and its arguments.
+fn parse_command(mut args: impl Iterator<Item = String>) -> Option<CliCommand> {
+    let Some(subcommand) = args.next() else {
+        return Some(CliCommand::Tui);
+    };
+    match subcommand.as_str() {
+        "capture" => {
+            let text = args.collect::<Vec<_>>().join(" ");
+            let text = text.trim();
+            if text.is_empty() {
+                return None;
+            }
+            Some(CliCommand::Capture(text.to_string()))
+        }
+        "view" => {
+            let sep = match (args.next().as_deref(), args.next()) {
+                (None, _) => None,
+                (Some("--sep"), Some(sep)) if !sep.is_empty() => Some(sep),
+                _ => return None,
+            };
+            if args.next().is_some() {
+                return None;
+            }
+            Some(CliCommand::View { sep })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(text: &str) -> Option<CliArgs> {
+        parse(text.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn theme_anywhere() {
+        for text in ["--theme light view", "view --theme light"] {
+            let args = parse_str(text).unwrap();
+            assert_eq!(args.theme.as_deref(), Some("light"));
+            assert!(matches!(args.command, CliCommand::View { sep: None }));
+        }
+        let args = parse_str("view --sep / --theme light").unwrap();
+        assert_eq!(args.theme.as_deref(), Some("light"));
+        assert!(
+            matches!(args.command, CliCommand::View { sep: Some(s) } if s == "/")
+        );
+        let args = parse_str("--theme light").unwrap();
+        assert!(matches!(args.command, CliCommand::Tui));
+    }
+
+    #[test]
+    fn theme_in_capture_text() {
+        let args = parse_str("capture buy --theme tickets").unwrap();
+        assert!(args.theme.is_none());
+        assert!(
+            matches!(args.command, CliCommand::Capture(t) if t == "buy --theme tickets")
+        );
+    }
+
+    #[test]
+    fn invalid_theme() {
+        assert!(parse_str("--theme").is_none());
+        assert!(parse_str("view --theme").is_none());
+        assert!(parse_str("--theme a --theme b").is_none());
+    }
+}
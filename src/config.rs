@@ -0,0 +1,141 @@
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, de::DeserializeOwned};
+
+use crate::{io::fs, message::keymap::KeysConfig};
+
+/// Settings for capturing entries from the command line.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CaptureConfig {
+    pub file: String,
+    pub inbox: Vec<String>,
+    pub timestamp: String,
+    pub lock_timeout: u64,
+}
+
+/// Settings for keyboard macros.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MacroConfig {
+    pub persist: bool,
+}
+
+/// Settings for the undo history.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    pub limit: usize,
+    pub keep: usize,
+}
+
+/// Settings for the display.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ViewConfig {
+    pub scrolloff: usize,
+    pub theme: String,
+}
+
+/// User settings read from the config file.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub capture: CaptureConfig,
+    pub keys: KeysConfig,
+    #[serde(rename = "macro")]
+    pub macros: MacroConfig,
+    pub history: HistoryConfig,
+    pub view: ViewConfig,
+}
+
+static CONFIG: OnceCell<Config> = OnceCell::new();
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            file: String::from("inbox"),
+            inbox: vec![String::from("Inbox")],
+            timestamp: String::from("[year]-[month]-[day] [hour]:[minute]"),
+            lock_timeout: 5,
+        }
+    }
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            limit: 1000,
+            keep: 100,
+        }
+    }
+}
+
+impl Default for ViewConfig {
+    fn default() -> Self {
+        Self {
+            scrolloff: 5,
+            theme: String::from("dark"),
+        }
+    }
+}
+
+/// Parse the TOML `text` of a settings `file`, or return a one-line error
+/// naming the file, the line and the problem.
+pub fn parse_toml<T: DeserializeOwned>(
+    text: &str,
+    file: &str,
+) -> Result<T, String> {
+    toml::from_str(text).map_err(|error| {
+        let line = error
+            .span()
+            .map_or(1, |span| text[..span.start].matches('\n').count() + 1);
+        let message = error.message().trim_end().replace('\n', " ");
+        format!("{file} line {line}: {message}")
+    })
+}
+
+/// Load the user config, falling back to the defaults if it is missing, or
+/// return an error if it is invalid. Must be called before `get`.
+pub fn init() -> Result<(), String> {
+    let config = match fs::read_config() {
+        Some(text) => parse_toml(&text, fs::CONFIG_FILE)?,
+        None => Config::default(),
+    };
+    if CONFIG.set(config).is_err() {
+        panic!("Config already loaded");
+    }
+    Ok(())
+}
+
+/// Return the user config.
+pub fn get() -> &'static Config {
+    CONFIG.get().expect("Config not loaded")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_errors_name_line() {
+        let text = "[history]\nlimit = 10\n\n[view]\nscrolloff = \"x\"\n";
+        let error = parse_toml::<Config>(text, "config.toml").err().unwrap();
+        assert_eq!(
+            error,
+            "config.toml line 5: invalid type: string \"x\", expected usize"
+        );
+        let error =
+            parse_toml::<Config>("[veiw]", "config.toml").err().unwrap();
+        assert!(error.starts_with("config.toml line 1: unknown field `veiw`"));
+        assert!(!error.contains('\n'));
+    }
+
+    #[test]
+    fn parse_partial_config() {
+        let config: Config =
+            parse_toml("[view]\nscrolloff = 2\n", "c").unwrap();
+        assert_eq!(config.view.scrolloff, 2);
+        assert_eq!(config.view.theme, "dark");
+        assert_eq!(config.history.limit, 1000);
+    }
+}
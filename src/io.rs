@@ -1,24 +1,45 @@
+pub mod data;
 pub mod fs;
 
-use std::{io::Result, path::Path};
+use std::{
+    fs::File,
+    io::{ErrorKind, Result, Write},
+    path::Path,
+    time::Duration,
+};
+
+use time::{OffsetDateTime, format_description};
 
 use crate::{
+    config,
+    io::data::DataFile,
     model::{
         ConfirmState, FileEntry, FilenameAction, FilenameState, FilenameStatus,
         ForestState, LoadState, Model, OpenDataFile, SessionState,
+        history::History, text_input::TextInput,
     },
+    outline::{self, ExportFormat},
     zipper::FocusNode,
 };
 
 /// A message indicating an IO action to perform.
 pub enum Command {
     None(Model),
-    Load { quit: bool },
+    Load {
+        quit: bool,
+    },
     InitSession(FileEntry),
     CheckFileExists(FilenameState),
     RenameFile(String, LoadState),
     SaveNew(Option<FocusNode>, String, SessionState),
+    SaveAs(String, SessionState),
     Save(SessionState),
+    Export {
+        format: ExportFormat,
+        path: String,
+        overwrite: bool,
+        state: SessionState,
+    },
     DeleteFile(LoadState),
     Quit,
 }
@@ -69,12 +90,20 @@ fn get_load_state() -> Option<LoadState> {
 fn init_session(file_entry: FileEntry) -> SessionState {
     let FileEntry { name, path } = file_entry;
     let file = fs::open_read_locked(&path);
-    let focus = bincode::deserialize(&fs::read_all_bytes(&file))
-        .expect("Failed to deserialize data");
+    let DataFile {
+        focus,
+        marks,
+        history,
+    } = data::decode(&fs::read_all_bytes(&file));
     let forest = ForestState {
         focus,
         changed: false,
     };
+    let limit = config::get().history.limit;
+    let history = history.map_or_else(
+        || History::new(limit),
+        |saved| History::restore(saved, &forest, limit),
+    );
     let open_file = OpenDataFile {
         name,
         path,
@@ -82,32 +111,128 @@ fn init_session(file_entry: FileEntry) -> SessionState {
     };
     SessionState {
         forest,
-        undo_stack: Vec::new(),
-        redo_stack: Vec::new(),
         maybe_file: Some(open_file),
+        marks,
+        history,
+        ..SessionState::new()
     }
 }
 
-// Write the forest to an existing file at `path`.
-fn write_to_file(focus: &Option<FocusNode>, path: &Path) {
+// Write the forest and marks to an existing file at `path`. The caller must
+// hold the file's lock, so that no other process changes its permissions or
+// contents meanwhile.
+fn write_to_file(data: &DataFile, path: &Path) {
     fs::set_read_only(path, false);
-    let file = fs::open_write_locked(path);
-    bincode::serialize_into(&file, focus).expect("Failed to serialize data");
+    let file = fs::open_truncated(path);
+    data::write(&file, data);
     fs::set_read_only(path, true);
 }
 
-// Save the current session.
+// Save the current session, releasing the file's lock once written.
 fn save(state: SessionState) {
-    let (focus, maybe_path) = state.unlock_state();
-    if let Some(path) = maybe_path {
-        write_to_file(&focus, &path);
+    let (data, maybe_file) = state.into_data_file();
+    if let Some(open_file) = maybe_file {
+        write_to_file(&data, &open_file.path);
     }
 }
 
-// Save the forest to `filename`.
-fn save_new(focus: &Option<FocusNode>, filename: &str) -> Result<()> {
+// Save the forest and marks to `filename`, returning the file still locked.
+fn save_new(data: &DataFile, filename: &str) -> Result<File> {
     let path = fs::create_new_file(filename)?;
-    write_to_file(focus, &path);
+    let file = fs::open_read_locked(&path);
+    write_to_file(data, &path);
+    Ok(file)
+}
+
+// Save the forest to a new file `filename` and switch the session to it.
+fn save_as(filename: &str, mut state: SessionState) -> SessionState {
+    if fs::filename_exists(filename) {
+        return state.set_alert("File Exists");
+    }
+    let data = DataFile {
+        focus: state.whole_forest().focus.map(FocusNode::focus_first_root),
+        marks: state.marks.clone(),
+        history: state.saved_history(),
+    };
+    let Ok(file) = save_new(&data, filename) else {
+        return state.set_alert("Invalid Filename");
+    };
+    state.maybe_file = Some(OpenDataFile {
+        name: filename.to_string(),
+        path: fs::app_file_path(filename),
+        _file: file,
+    });
+    state.forest.changed = false;
+    state.marks_changed = false;
+    state.set_alert(format!("Saved as {filename}"))
+}
+
+// Export the forest as text in the `format` to the file at `path`, which
+// must not exist unless `overwrite` is set.
+fn export(
+    format: ExportFormat,
+    path: &str,
+    overwrite: bool,
+    state: SessionState,
+) -> SessionState {
+    let focus = state.whole_forest().focus;
+    let trees = focus.as_ref().map(FocusNode::to_trees).unwrap_or_default();
+    let text = outline::export(&trees, format);
+    let result = if overwrite {
+        std::fs::write(path, text)
+    } else {
+        File::create_new(path)
+            .and_then(|mut file| file.write_all(text.as_bytes()))
+    };
+    match result {
+        Ok(()) => state.set_alert(format!("Exported to {path}")),
+        Err(error) if error.kind() == ErrorKind::AlreadyExists => {
+            state.set_alert("File Exists, use export! to overwrite")
+        }
+        Err(error) => state.set_alert(format!("Export failed: {error}")),
+    }
+}
+
+// Prefix `text` with the current time in the given `format`, if not empty.
+fn timestamped(text: &str, format: &str) -> String {
+    if format.is_empty() {
+        return text.to_string();
+    }
+    let description =
+        format_description::parse(format).expect("Invalid timestamp format");
+    let now = OffsetDateTime::now_local()
+        .unwrap_or_else(|_| OffsetDateTime::now_utc());
+    let stamp = now
+        .format(&description)
+        .expect("Failed to format timestamp");
+    format!("{stamp} {text}")
+}
+
+/// Append `text` as a timestamped entry under the configured inbox path.
+pub fn capture(text: &str) -> Result<()> {
+    let config = &config::get().capture;
+    let path = fs::app_file_path(&config.file);
+    match fs::create_new_file(&config.file) {
+        Err(error) if error.kind() != ErrorKind::AlreadyExists => {
+            return Err(error);
+        }
+        _ => {}
+    }
+    let timeout = Duration::from_secs(config.lock_timeout);
+    let file = fs::open_read_locked_wait(&path, timeout)?;
+    let DataFile {
+        focus,
+        marks,
+        history,
+    } = data::decode(&fs::read_all_bytes(&file));
+    let label = timestamped(text, &config.timestamp);
+    let focus = FocusNode::append_at_path(focus, &config.inbox, label);
+    let data = DataFile {
+        focus: Some(focus),
+        marks,
+        history,
+    };
+    write_to_file(&data, &path);
     Ok(())
 }
 
@@ -146,27 +271,41 @@ pub fn execute_command(command: Command) -> Option<Model> {
                 return Some(Model::Load(load_state));
             };
             let filename_state = FilenameState {
-                input: filename,
+                input: TextInput::from(filename),
                 status,
                 action: FilenameAction::Rename(load_state),
             };
             Model::FilenameInput(filename_state)
         }
         Command::SaveNew(initial_focus, filename, session) => {
+            let data = DataFile {
+                focus: initial_focus,
+                marks: session.marks.clone(),
+                history: session.saved_history(),
+            };
             let status = if fs::filename_exists(&filename) {
                 FilenameStatus::Exists
-            } else if save_new(&initial_focus, &filename).is_err() {
+            } else if save_new(&data, &filename).is_err() {
                 FilenameStatus::Invalid
             } else {
                 return execute_command(Command::Load { quit: true });
             };
             let filename_state = FilenameState {
-                input: filename,
+                input: TextInput::from(filename),
                 status,
-                action: FilenameAction::SaveNew(session),
+                action: FilenameAction::SaveNew(Box::new(session)),
             };
             Model::FilenameInput(filename_state)
         }
+        Command::SaveAs(filename, state) => {
+            Model::Normal(save_as(&filename, state))
+        }
+        Command::Export {
+            format,
+            path,
+            overwrite,
+            state,
+        } => Model::Normal(export(format, &path, overwrite, state)),
         Command::Save(state) => {
             save(state);
             return execute_command(Command::Load { quit: true });
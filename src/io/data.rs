@@ -0,0 +1,95 @@
+use std::{collections::BTreeMap, fs::File, io::Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    model::history::SavedHistory,
+    zipper::{FocusNode, legacy},
+};
+
+// Bytes starting every data file written with a version header.
+const MAGIC: &[u8] = b"ELMLOG";
+const VERSION: u8 = 1;
+
+/// The forest, marks and undo history saved in a data file.
+#[derive(Default, Serialize, Deserialize)]
+pub struct DataFile {
+    pub focus: Option<FocusNode>,
+    pub marks: BTreeMap<char, u64>,
+    pub history: Option<SavedHistory>,
+}
+
+/// Parse the bytes of a data file, including files saved before the version
+/// header, which hold only the forest.
+pub fn decode(bytes: &[u8]) -> DataFile {
+    if bytes.is_empty() {
+        return DataFile::default();
+    }
+    let Some(rest) = bytes.strip_prefix(MAGIC) else {
+        let focus: Option<legacy::FocusNode> =
+            bincode::deserialize(bytes).expect("Failed to deserialize data");
+        return DataFile {
+            focus: focus.map(FocusNode::from),
+            ..DataFile::default()
+        };
+    };
+    match rest.split_first() {
+        Some((&VERSION, data)) => {
+            bincode::deserialize(data).expect("Failed to deserialize data")
+        }
+        _ => panic!("Unsupported data file version"),
+    }
+}
+
+/// Write the `data` to the `file` after the version header.
+pub fn write(mut file: &File, data: &DataFile) {
+    file.write_all(MAGIC)
+        .and_then(|()| file.write_all(&[VERSION]))
+        .expect("Failed to write data");
+    bincode::serialize_into(file, data).expect("Failed to serialize data");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model::history::History,
+        test_util::{label, state},
+    };
+
+    #[test]
+    fn round_trip() {
+        let before = state("before");
+        let after = state("after");
+        let mut history = History::new(10);
+        history.push(before, &after);
+        let data = DataFile {
+            focus: after.focus.clone(),
+            marks: BTreeMap::from([('a', 1)]),
+            history: history.save(10),
+        };
+        let path = std::env::temp_dir()
+            .join(format!("elmlog-data-test-{}", std::process::id()));
+        let file = File::create(&path).unwrap();
+        write(&file, &data);
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(bytes.starts_with(MAGIC));
+        let DataFile {
+            focus,
+            marks,
+            history,
+        } = decode(&bytes);
+        assert!(focus == after.focus);
+        assert_eq!(marks, data.marks);
+        let mut history = History::restore(history.unwrap(), &after, 10);
+        let undone = history.undo(after.clone());
+        assert_eq!(label(&undone), "before");
+    }
+
+    #[test]
+    fn empty_file() {
+        let data = decode(&[]);
+        assert!(data.focus.is_none() && data.history.is_none());
+    }
+}
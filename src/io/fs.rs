@@ -1,12 +1,20 @@
 use std::{
     fs::{self, File, OpenOptions},
-    io::{Read, Result},
+    io::{Error, ErrorKind, Read, Result},
     path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
 };
 
 use fs2::FileExt;
 
 const APP_DIR: &str = "elmlog";
+/// Name of the config file in the config directory.
+pub const CONFIG_FILE: &str = "config.toml";
+/// Name of the saved macros file in the config directory.
+pub const MACROS_FILE: &str = "macros.toml";
+const THEMES_DIR: &str = "themes";
+const LOCK_RETRY: Duration = Duration::from_millis(50);
 
 // Return the application directory path, creating any missing directories.
 fn app_dir_path() -> PathBuf {
@@ -16,6 +24,39 @@ fn app_dir_path() -> PathBuf {
     path
 }
 
+/// Return the contents of the config file, if it exists.
+pub fn read_config() -> Option<String> {
+    let config_dir = dirs::config_dir()?;
+    fs::read_to_string(config_dir.join(APP_DIR).join(CONFIG_FILE)).ok()
+}
+
+/// Return the contents of the theme file called `name`, if it exists.
+pub fn read_theme(name: &str) -> Option<String> {
+    let config_dir = dirs::config_dir()?;
+    let path = config_dir.join(APP_DIR).join(THEMES_DIR);
+    fs::read_to_string(path.join(name).with_extension("toml")).ok()
+}
+
+/// Return the contents of the saved macros file, if it exists.
+pub fn read_macros() -> Option<String> {
+    let config_dir = dirs::config_dir()?;
+    fs::read_to_string(config_dir.join(APP_DIR).join(MACROS_FILE)).ok()
+}
+
+/// Write `text` to the macros file, creating the config directory if needed.
+pub fn write_macros(text: &str) -> Result<()> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "No config directory"))?
+        .join(APP_DIR);
+    fs::create_dir_all(&config_dir)?;
+    fs::write(config_dir.join(MACROS_FILE), text)
+}
+
+/// Return the path of `filename` in the app directory.
+pub fn app_file_path(filename: &str) -> PathBuf {
+    app_dir_path().join(filename)
+}
+
 /// Rename a file and return its new path.
 pub fn rename_file(old_path: &Path, filename: &str) -> Result<PathBuf> {
     let new_path = app_dir_path().join(filename);
@@ -58,15 +99,30 @@ pub fn open_read_locked(path: &Path) -> File {
     file
 }
 
-/// Open a file in write mode and lock it.
-pub fn open_write_locked(path: &Path) -> File {
-    let file = OpenOptions::new()
+/// Open a file in write mode, truncating it. The caller must already hold
+/// the file's lock through another handle.
+pub fn open_truncated(path: &Path) -> File {
+    OpenOptions::new()
         .write(true)
         .truncate(true)
         .open(path)
-        .expect("Failed to write to file");
-    lock(&file);
-    file
+        .expect("Failed to write to file")
+}
+
+/// Open a file in read mode, waiting up to `timeout` for its lock.
+pub fn open_read_locked_wait(path: &Path, timeout: Duration) -> Result<File> {
+    let file = OpenOptions::new().read(true).open(path)?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        match file.try_lock_exclusive() {
+            Ok(()) => return Ok(file),
+            Err(_) if Instant::now() >= deadline => {
+                let message = "File is currently locked";
+                return Err(Error::new(ErrorKind::WouldBlock, message));
+            }
+            Err(_) => thread::sleep(LOCK_RETRY),
+        }
+    }
 }
 
 /// Check whether `filename` exists in the app directory.
@@ -1,27 +1,48 @@
+mod cli;
+mod config;
 mod io;
 mod message;
 mod model;
+mod outline;
+#[cfg(test)]
+mod test_util;
 mod update;
 mod view;
 mod zipper;
 
-use std::io::Result;
+use std::io::{Result, read_to_string, stdin, stdout};
 
+use crossterm::{
+    event::{
+        DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste,
+        EnableMouseCapture,
+    },
+    execute,
+};
 use ratatui::DefaultTerminal;
 
 use crate::{
-    io::{Command, execute_command},
-    message::handle_input,
+    cli::{CliArgs, CliCommand},
+    io::{Command, capture, execute_command},
+    message::{handle_input, keymap, macros::Macros, mouse::MouseState},
+    model::{Model, SessionState},
     update::update,
     view::view,
+    zipper::FocusNode,
 };
 
-fn run(terminal: &mut DefaultTerminal) -> Result<()> {
+fn run(
+    terminal: &mut DefaultTerminal,
+    mut model: Model,
+    mut macros: Macros,
+) -> Result<()> {
     terminal.clear()?;
-    let mut model = execute_command(Command::Load { quit: false }).unwrap();
+    let mut click_map = Default::default();
+    let mut mouse_state = MouseState::default();
     loop {
-        terminal.draw(|frame| view(&model, frame))?;
-        let message = handle_input(model)?;
+        terminal.draw(|frame| click_map = view(&model, frame, &click_map))?;
+        let message =
+            handle_input(model, &click_map, &mut mouse_state, &mut macros)?;
         let command = update(message);
         model = match execute_command(command) {
             Some(model) => model,
@@ -30,6 +51,65 @@ fn run(terminal: &mut DefaultTerminal) -> Result<()> {
     }
 }
 
+// Run the TUI with mouse capture and bracketed paste enabled, after
+// printing any warnings about the saved macros.
+fn run_tui(model: Model) -> Result<()> {
+    let (macros, warnings) = Macros::load();
+    for warning in warnings {
+        eprintln!("elmlog: {warning}");
+    }
+    ratatui::run(|terminal| {
+        execute!(stdout(), EnableMouseCapture, EnableBracketedPaste)?;
+        let result = run(terminal, model, macros);
+        execute!(stdout(), DisableMouseCapture, DisableBracketedPaste)?;
+        result
+    })
+}
+
+// Parse stdin into a forest of paths split on `sep`, or of indented lines.
+//
+// Keyboard input is read from /dev/tty once stdin is no longer a terminal.
+fn read_forest(sep: Option<String>) -> Result<Option<FocusNode>> {
+    let text = read_to_string(stdin())?;
+    let trees = match sep {
+        Some(sep) => outline::parse_paths(&text, &sep),
+        None => outline::parse_indented(&text),
+    };
+    Ok(FocusNode::from_trees(trees))
+}
+
+// Load the config, and the keymap and `theme` if the `command` runs the TUI,
+// so that invalid settings are reported before the terminal is set up.
+fn load_settings(
+    theme: Option<&str>,
+    command: &CliCommand,
+) -> std::result::Result<(), String> {
+    config::init()?;
+    if let CliCommand::Capture(_) = command {
+        return Ok(());
+    }
+    keymap::init()?;
+    view::style::init(theme)
+}
+
 fn main() -> Result<()> {
-    ratatui::run(run)
+    let Some(CliArgs { theme, command }) = cli::parse_args() else {
+        eprintln!("{}", cli::USAGE);
+        std::process::exit(2);
+    };
+    if let Err(error) = load_settings(theme.as_deref(), &command) {
+        eprintln!("elmlog: {error}");
+        std::process::exit(1);
+    }
+    match command {
+        CliCommand::Tui => {
+            let model = execute_command(Command::Load { quit: false }).unwrap();
+            run_tui(model)
+        }
+        CliCommand::Capture(text) => capture(&text),
+        CliCommand::View { sep } => {
+            let session = SessionState::new_read_only(read_forest(sep)?);
+            run_tui(Model::Normal(session))
+        }
+    }
 }
@@ -1,13 +1,28 @@
+pub mod keymap;
+pub mod macros;
+pub mod mouse;
+
 use std::io::Result;
 
-use crossterm::event::{self, KeyCode, KeyEventKind};
+use crossterm::event::{self, KeyCode, KeyEventKind, KeyModifiers};
 
-use crate::model::{
-    ConfirmState, FilenameState, LabelState, LoadState, Model, SaveState,
-    SessionState,
+use crate::{
+    message::{
+        keymap::{CommandAction, InputAction, Key},
+        macros::Macros,
+        mouse::MouseState,
+    },
+    model::{
+        CommandLineState, ConfirmState, FilenameState, LabelState, LoadState,
+        MarkState, Model, PasteState, SaveState, SearchState, SessionState,
+        VisualState,
+        help::{self, HelpState},
+    },
+    view::ClickMap,
 };
 
 /// A message sent in Load mode.
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum LoadMsg {
     Decrement,
     Increment,
@@ -19,23 +34,73 @@ pub enum LoadMsg {
 }
 
 /// A message sent in Normal mode.
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum NormalMsg {
     Ascend,
     Descend,
     Previous,
     Next,
+    FirstSibling,
+    LastSibling,
+    Top,
+    Bottom,
+    PrevVisible,
+    NextVisible,
+    ParentNext,
+    PageUp,
+    PageDown,
     Edit,
     Insert,
     Move,
+    Visual,
     Nest,
     Flatten,
     Delete,
+    Put,
     Undo,
     Redo,
+    Older,
+    Newer,
+    Fold,
+    Hoist,
+    Unhoist,
+    UnhoistAll,
+    Search,
+    NextMatch,
+    PrevMatch,
+    SetMark,
+    JumpMark,
+    JumpBack,
+    JumpForward,
+    CommandLine,
+    Repeat,
     Quit,
 }
 
+impl NormalMsg {
+    /// Return whether the message modifies the forest.
+    pub fn is_edit(&self) -> bool {
+        matches!(
+            self,
+            Self::Edit
+                | Self::Insert
+                | Self::Move
+                | Self::Visual
+                | Self::Nest
+                | Self::Flatten
+                | Self::Delete
+                | Self::Put
+                | Self::Undo
+                | Self::Redo
+                | Self::Older
+                | Self::Newer
+                | Self::Repeat
+        )
+    }
+}
+
 /// A message sent in Insert mode.
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum InsertMsg {
     Parent,
     Child,
@@ -45,6 +110,7 @@ pub enum InsertMsg {
 }
 
 /// A message sent in Move mode.
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum MoveMsg {
     Promote,
     Demote,
@@ -53,26 +119,61 @@ pub enum MoveMsg {
     Done,
 }
 
+/// A message sent in Visual mode.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum VisualMsg {
+    Previous,
+    Next,
+    Backward,
+    Forward,
+    Promote,
+    Demote,
+    Wrap,
+    Yank,
+    Delete,
+    Sort,
+    Cancel,
+}
+
 /// Type of edit to apply to the user input text.
 pub enum InputEdit {
     Append(char),
     PopChar,
+    Delete,
+    DeleteWord,
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    Home,
+    End,
+    Paste(String),
 }
 
 /// A message sent in Label Input mode.
 pub enum LabelMsg {
     Edit(InputEdit),
+    PasteLines(String),
     Submit,
     Cancel,
 }
 
 /// A message sent in Save mode.
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum SaveMsg {
     Toggle,
     Confirm,
     Cancel,
 }
 
+/// A message sent in Paste mode.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PasteMsg {
+    Toggle,
+    Confirm,
+    Cancel,
+}
+
 /// A message sent in Filename Input mode.
 pub enum FilenameMsg {
     Edit(InputEdit),
@@ -80,7 +181,42 @@ pub enum FilenameMsg {
     Cancel,
 }
 
+/// A message sent in Search mode.
+pub enum SearchMsg {
+    Edit(InputEdit),
+    Submit,
+    Cancel,
+}
+
+/// A message sent in Command Line mode.
+pub enum CommandLineMsg {
+    Edit(InputEdit),
+    Complete,
+    Older,
+    Newer,
+    Submit,
+    Cancel,
+}
+
+/// A message sent in Mark mode.
+pub enum MarkMsg {
+    Select(char),
+    Cancel,
+}
+
+/// A message sent in the help overlay.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum HelpMsg {
+    Toggle,
+    Close,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+}
+
 /// A message sent in Confirm mode.
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum ConfirmMsg {
     Confirm,
     Cancel,
@@ -92,149 +228,315 @@ pub enum Message {
     Normal(NormalMsg, SessionState),
     Insert(InsertMsg, SessionState),
     Move(MoveMsg, SessionState),
+    Visual(VisualMsg, VisualState),
     LabelInput(LabelMsg, LabelState),
+    Paste(PasteMsg, PasteState),
     Save(SaveMsg, SaveState),
     FilenameInput(FilenameMsg, FilenameState),
+    Search(SearchMsg, SearchState),
+    CommandLine(CommandLineMsg, CommandLineState),
+    Mark(MarkMsg, MarkState),
+    Help(HelpMsg, HelpState),
     Confirm(ConfirmMsg, ConfirmState),
+    FocusNode(Vec<usize>, SessionState),
+    SelectFile(usize, LoadState),
     Continue(Model),
 }
 
+// Meaning of a key in a text input mode.
+enum InputKey {
+    Edit(InputEdit),
+    Submit,
+    Cancel,
+}
+
+// Map a `key` to its meaning in a text input mode.
+fn to_input_key(key: Key) -> Option<InputKey> {
+    let input_key = match keymap::get().input.lookup(key) {
+        Some(InputAction::Submit) => InputKey::Submit,
+        Some(InputAction::Cancel) => InputKey::Cancel,
+        Some(InputAction::PopChar) => InputKey::Edit(InputEdit::PopChar),
+        Some(InputAction::Delete) => InputKey::Edit(InputEdit::Delete),
+        Some(InputAction::DeleteWord) => InputKey::Edit(InputEdit::DeleteWord),
+        Some(InputAction::Left) => InputKey::Edit(InputEdit::Left),
+        Some(InputAction::Right) => InputKey::Edit(InputEdit::Right),
+        Some(InputAction::WordLeft) => InputKey::Edit(InputEdit::WordLeft),
+        Some(InputAction::WordRight) => InputKey::Edit(InputEdit::WordRight),
+        Some(InputAction::Home) => InputKey::Edit(InputEdit::Home),
+        Some(InputAction::End) => InputKey::Edit(InputEdit::End),
+        None => match key {
+            Key {
+                code: KeyCode::Char(c),
+                modifiers: KeyModifiers::NONE,
+            } => InputKey::Edit(InputEdit::Append(c)),
+            _ => return None,
+        },
+    };
+    Some(input_key)
+}
+
 // Map a `key` to a Message in Load mode.
-fn to_load_msg(key: KeyCode, state: LoadState) -> Message {
-    let msg = match key {
-        KeyCode::Char('k') => LoadMsg::Decrement,
-        KeyCode::Char('j') => LoadMsg::Increment,
-        KeyCode::Char('n') => LoadMsg::New,
-        KeyCode::Char('r') => LoadMsg::Rename,
-        KeyCode::Char('d') => LoadMsg::Delete,
-        KeyCode::Char('q') => LoadMsg::Quit,
-        KeyCode::Down => LoadMsg::Increment,
-        KeyCode::Up => LoadMsg::Decrement,
-        KeyCode::Enter => LoadMsg::Open,
-        _ => return Message::Continue(Model::Load(state)),
+fn to_load_msg(key: Key, state: LoadState) -> Message {
+    match keymap::get().load.lookup(key) {
+        Some(msg) => Message::Load(msg, state),
+        None => Message::Continue(Model::Load(state)),
+    }
+}
+
+// Return the digit extending the pending `count`, if `key` types one.
+fn to_count_digit(key: Key, count: Option<usize>) -> Option<usize> {
+    let Key {
+        code: KeyCode::Char(c),
+        modifiers: KeyModifiers::NONE,
+    } = key
+    else {
+        return None;
     };
-    Message::Load(msg, state)
+    let digit = c.to_digit(10)? as usize;
+    (digit > 0 || count.is_some()).then_some(digit)
+}
+
+// Extend or clear the pending count of a session given an unbound `key`.
+fn update_count(key: Key, state: SessionState) -> SessionState {
+    match to_count_digit(key, state.count) {
+        Some(digit) => state.push_digit(digit),
+        None => state.clear_count(),
+    }
 }
 
 // Map a `key` to a Message in Normal mode.
-fn to_normal_msg(key: KeyCode, state: SessionState) -> Message {
-    let msg = match key {
-        KeyCode::Char('h') => NormalMsg::Ascend,
-        KeyCode::Char('l') => NormalMsg::Descend,
-        KeyCode::Char('k') => NormalMsg::Previous,
-        KeyCode::Char('j') => NormalMsg::Next,
-        KeyCode::Char('e') => NormalMsg::Edit,
-        KeyCode::Char('i') => NormalMsg::Insert,
-        KeyCode::Char('m') => NormalMsg::Move,
-        KeyCode::Char('n') => NormalMsg::Nest,
-        KeyCode::Char('f') => NormalMsg::Flatten,
-        KeyCode::Char('d') => NormalMsg::Delete,
-        KeyCode::Char('u') => NormalMsg::Undo,
-        KeyCode::Char('r') => NormalMsg::Redo,
-        KeyCode::Char('q') => NormalMsg::Quit,
-        KeyCode::Left => NormalMsg::Ascend,
-        KeyCode::Right => NormalMsg::Descend,
-        KeyCode::Up => NormalMsg::Previous,
-        KeyCode::Down => NormalMsg::Next,
-        _ => return Message::Continue(Model::Normal(state)),
-    };
-    Message::Normal(msg, state)
+fn to_normal_msg(key: Key, state: SessionState) -> Message {
+    let state = state.clear_alert();
+    match keymap::get().normal.lookup(key) {
+        Some(msg) if !(state.read_only && msg.is_edit()) => {
+            Message::Normal(msg, state)
+        }
+        Some(_) => Message::Continue(Model::Normal(state.clear_count())),
+        None => Message::Continue(Model::Normal(update_count(key, state))),
+    }
 }
 
 // Map a `key` to a Message in Insert mode.
-fn to_insert_msg(key: KeyCode, state: SessionState) -> Message {
-    let msg = match key {
-        KeyCode::Char('h') => InsertMsg::Parent,
-        KeyCode::Char('l') => InsertMsg::Child,
-        KeyCode::Char('k') => InsertMsg::Before,
-        KeyCode::Char('j') => InsertMsg::After,
-        KeyCode::Char(' ') => InsertMsg::Cancel,
-        _ => return Message::Continue(Model::Insert(state)),
-    };
-    Message::Insert(msg, state)
+fn to_insert_msg(key: Key, state: SessionState) -> Message {
+    match keymap::get().insert.lookup(key) {
+        Some(msg) => Message::Insert(msg, state),
+        None => Message::Continue(Model::Insert(state)),
+    }
 }
 
 // Map a `key` to a Message in Move mode.
-fn to_move_msg(key: KeyCode, state: SessionState) -> Message {
-    let msg = match key {
-        KeyCode::Char('h') | KeyCode::Left => MoveMsg::Promote,
-        KeyCode::Char('l') | KeyCode::Right => MoveMsg::Demote,
-        KeyCode::Char('k') | KeyCode::Up => MoveMsg::Backward,
-        KeyCode::Char('j') | KeyCode::Down => MoveMsg::Forward,
-        KeyCode::Char(' ') => MoveMsg::Done,
-        _ => return Message::Continue(Model::Move(state)),
-    };
-    Message::Move(msg, state)
+fn to_move_msg(key: Key, state: SessionState) -> Message {
+    match keymap::get().move_mode.lookup(key) {
+        Some(msg) => Message::Move(msg, state),
+        None => Message::Continue(Model::Move(update_count(key, state))),
+    }
+}
+
+// Map a `key` to a Message in Visual mode.
+fn to_visual_msg(key: Key, mut state: VisualState) -> Message {
+    match keymap::get().visual.lookup(key) {
+        Some(msg) => Message::Visual(msg, state),
+        None => {
+            state.session = update_count(key, state.session);
+            Message::Continue(Model::Visual(state))
+        }
+    }
 }
 
 // Map a `key` to a Message in Label Input mode.
-fn to_label_input_msg(key: KeyCode, state: LabelState) -> Message {
-    let msg = match key {
-        KeyCode::Char(c) => LabelMsg::Edit(InputEdit::Append(c)),
-        KeyCode::Backspace => LabelMsg::Edit(InputEdit::PopChar),
-        KeyCode::Enter => LabelMsg::Submit,
-        KeyCode::Tab => LabelMsg::Cancel,
-        _ => return Message::Continue(Model::LabelInput(state)),
+fn to_label_input_msg(key: Key, state: LabelState) -> Message {
+    let msg = match to_input_key(key) {
+        Some(InputKey::Edit(edit)) => LabelMsg::Edit(edit),
+        Some(InputKey::Submit) => LabelMsg::Submit,
+        Some(InputKey::Cancel) => LabelMsg::Cancel,
+        None => return Message::Continue(Model::LabelInput(state)),
     };
     Message::LabelInput(msg, state)
 }
 
 // Map a `key` to a Message in Save mode.
-fn to_save_msg(key: KeyCode, state: SaveState) -> Message {
-    let msg = match key {
-        KeyCode::Char('j') => SaveMsg::Toggle,
-        KeyCode::Enter => SaveMsg::Confirm,
-        KeyCode::Char(' ') => SaveMsg::Cancel,
-        _ => return Message::Continue(Model::Save(state)),
-    };
-    Message::Save(msg, state)
+fn to_save_msg(key: Key, state: SaveState) -> Message {
+    match keymap::get().save.lookup(key) {
+        Some(msg) => Message::Save(msg, state),
+        None => Message::Continue(Model::Save(state)),
+    }
+}
+
+// Map a `key` to a Message in Paste mode.
+fn to_paste_msg(key: Key, state: PasteState) -> Message {
+    match keymap::get().paste.lookup(key) {
+        Some(msg) => Message::Paste(msg, state),
+        None => Message::Continue(Model::Paste(state)),
+    }
 }
 
 // Map a `key` to a Message in Filename Input mode.
-fn to_filename_input_msg(key: KeyCode, state: FilenameState) -> Message {
-    let msg = match key {
-        KeyCode::Char(c) => FilenameMsg::Edit(InputEdit::Append(c)),
-        KeyCode::Backspace => FilenameMsg::Edit(InputEdit::PopChar),
-        KeyCode::Enter => FilenameMsg::Submit,
-        KeyCode::Tab => FilenameMsg::Cancel,
-        _ => return Message::Continue(Model::FilenameInput(state)),
+fn to_filename_input_msg(key: Key, state: FilenameState) -> Message {
+    let msg = match to_input_key(key) {
+        Some(InputKey::Edit(edit)) => FilenameMsg::Edit(edit),
+        Some(InputKey::Submit) => FilenameMsg::Submit,
+        Some(InputKey::Cancel) => FilenameMsg::Cancel,
+        None => return Message::Continue(Model::FilenameInput(state)),
     };
     Message::FilenameInput(msg, state)
 }
 
-// Map a `key` to a Message in Confirm mode.
-fn to_confirm_msg(key: KeyCode, state: ConfirmState) -> Message {
+// Map a `key` to a Message in Search mode.
+fn to_search_msg(key: Key, state: SearchState) -> Message {
+    let msg = match to_input_key(key) {
+        Some(InputKey::Edit(edit)) => SearchMsg::Edit(edit),
+        Some(InputKey::Submit) => SearchMsg::Submit,
+        Some(InputKey::Cancel) => SearchMsg::Cancel,
+        None => return Message::Continue(Model::Search(state)),
+    };
+    Message::Search(msg, state)
+}
+
+// Map a `key` to a Message in Command Line mode.
+fn to_command_line_msg(key: Key, state: CommandLineState) -> Message {
+    let msg = match keymap::get().command.lookup(key) {
+        Some(CommandAction::Submit) => CommandLineMsg::Submit,
+        Some(CommandAction::Cancel) => CommandLineMsg::Cancel,
+        Some(CommandAction::Complete) => CommandLineMsg::Complete,
+        Some(CommandAction::Older) => CommandLineMsg::Older,
+        Some(CommandAction::Newer) => CommandLineMsg::Newer,
+        Some(CommandAction::PopChar) if state.input.is_empty() => {
+            CommandLineMsg::Cancel
+        }
+        Some(CommandAction::PopChar) => {
+            CommandLineMsg::Edit(InputEdit::PopChar)
+        }
+        None => match to_input_key(key) {
+            Some(InputKey::Edit(edit)) => CommandLineMsg::Edit(edit),
+            Some(InputKey::Submit) => CommandLineMsg::Submit,
+            Some(InputKey::Cancel) => CommandLineMsg::Cancel,
+            None => return Message::Continue(Model::CommandLine(state)),
+        },
+    };
+    Message::CommandLine(msg, state)
+}
+
+// Map a `key` to a Message in Mark mode, cancelling unless it is a letter.
+fn to_mark_msg(key: Key, state: MarkState) -> Message {
     let msg = match key {
-        KeyCode::Enter => ConfirmMsg::Confirm,
-        KeyCode::Char(' ') => ConfirmMsg::Cancel,
-        _ => return Message::Continue(Model::Confirm(state)),
+        Key {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::NONE,
+        } if c.is_alphabetic() => MarkMsg::Select(c),
+        _ => MarkMsg::Cancel,
     };
-    Message::Confirm(msg, state)
+    Message::Mark(msg, state)
 }
 
-// Map a pressed `key` to a Message based on the current `model`.
-fn key_to_message(model: Model, key: KeyCode) -> Message {
+// Map a `key` to a Message in the help overlay.
+fn to_help_msg(key: Key, state: HelpState) -> Message {
+    match keymap::get().help.lookup(key) {
+        Some(msg) => Message::Help(msg, state),
+        None => Message::Continue(Model::Help(state)),
+    }
+}
+
+// Map a `key` to a Message in Confirm mode.
+fn to_confirm_msg(key: Key, state: ConfirmState) -> Message {
+    match keymap::get().confirm.lookup(key) {
+        Some(msg) => Message::Confirm(msg, state),
+        None => Message::Continue(Model::Confirm(state)),
+    }
+}
+
+// Map a pressed `key` to a Message based on the current `model`, opening
+// the help overlay in modes whose keys do not enter text.
+fn key_to_message(model: Model, key: Key) -> Message {
+    let help = match keymap::get().help.lookup(key) {
+        Some(HelpMsg::Toggle) => help::mode_entries(&model),
+        _ => None,
+    };
+    if let Some((title, entries)) = help {
+        let help_state = HelpState::new(title, entries, model);
+        return Message::Continue(Model::Help(help_state));
+    }
     match model {
         Model::Load(load_state) => to_load_msg(key, load_state),
         Model::Normal(session_state) => to_normal_msg(key, session_state),
         Model::Insert(session_state) => to_insert_msg(key, session_state),
         Model::Move(session_state) => to_move_msg(key, session_state),
+        Model::Visual(visual_state) => to_visual_msg(key, visual_state),
         Model::Save(save_state) => to_save_msg(key, save_state),
         Model::LabelInput(label_state) => to_label_input_msg(key, label_state),
+        Model::Paste(paste_state) => to_paste_msg(key, paste_state),
         Model::FilenameInput(filename_state) => {
             to_filename_input_msg(key, filename_state)
         }
+        Model::Search(search_state) => to_search_msg(key, search_state),
+        Model::CommandLine(command_line_state) => {
+            to_command_line_msg(key, command_line_state)
+        }
+        Model::Mark(mark_state) => to_mark_msg(key, mark_state),
+        Model::Help(help_state) => to_help_msg(key, help_state),
         Model::Confirm(confirm_state) => to_confirm_msg(key, confirm_state),
     }
 }
 
-/// Convert a user input event into a Message based on the current `model`.
-pub fn handle_input(model: Model) -> Result<Message> {
-    let event::Event::Key(key) = event::read()? else {
-        return Ok(Message::Continue(model));
+// Map pasted `text` to a Message based on the current `model`, joining its
+// lines unless they can be inserted as separate items.
+fn paste_to_message(model: Model, text: String) -> Message {
+    let text = text.trim_end_matches(['\r', '\n']);
+    let is_multiline = text.contains(['\r', '\n']);
+    let edit = InputEdit::Paste(text.lines().collect::<Vec<_>>().join(" "));
+    match model {
+        Model::LabelInput(state) if is_multiline => {
+            Message::LabelInput(LabelMsg::PasteLines(text.to_string()), state)
+        }
+        Model::LabelInput(state) => {
+            Message::LabelInput(LabelMsg::Edit(edit), state)
+        }
+        Model::FilenameInput(state) => {
+            Message::FilenameInput(FilenameMsg::Edit(edit), state)
+        }
+        Model::Search(state) => Message::Search(SearchMsg::Edit(edit), state),
+        Model::CommandLine(state) => {
+            Message::CommandLine(CommandLineMsg::Edit(edit), state)
+        }
+        model => Message::Continue(model),
+    }
+}
+
+// Store the height of the scrolled view for paging in a Normal mode or help
+// overlay `model`.
+fn set_page_height(mut model: Model, click_map: &ClickMap) -> Model {
+    match &mut model {
+        Model::Normal(state) => state.page_height = click_map.height(),
+        Model::Help(state) => state.page_height = click_map.height(),
+        _ => {}
+    }
+    model
+}
+
+/// Convert a user input event, or the next key of a macro being replayed,
+/// into a Message based on the current `model` and the `click_map` of the
+/// last frame.
+pub fn handle_input(
+    model: Model,
+    click_map: &ClickMap,
+    mouse_state: &mut MouseState,
+    macros: &mut Macros,
+) -> Result<Message> {
+    let model = set_page_height(model, click_map);
+    if let Some(key) = macros.next_key() {
+        return Ok(macros.key_to_message(model, key, false));
+    }
+    let key = match event::read()? {
+        event::Event::Key(key) => key,
+        event::Event::Mouse(mouse_event) => {
+            let message =
+                mouse::to_message(model, mouse_event, click_map, mouse_state);
+            return Ok(message);
+        }
+        event::Event::Paste(text) => return Ok(paste_to_message(model, text)),
+        _ => return Ok(Message::Continue(model)),
     };
     if key.kind != KeyEventKind::Press {
         return Ok(Message::Continue(model));
     }
-    Ok(key_to_message(model, key.code))
+    Ok(macros.key_to_message(model, Key::from_event(key), true))
 }
@@ -0,0 +1,1266 @@
+use std::collections::BTreeMap;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+
+use crate::{
+    config,
+    io::fs,
+    message::{
+        ConfirmMsg, HelpMsg, InsertMsg, LoadMsg, MoveMsg, NormalMsg, PasteMsg,
+        SaveMsg, VisualMsg,
+    },
+};
+
+/// A key press with its modifiers, ignoring Shift on characters.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+/// A message that can be bound to keys in the config file.
+pub trait Action: Copy + Eq + 'static {
+    /// Every action of this type.
+    const ALL: &'static [Self];
+
+    /// Name identifying the action in the config file.
+    fn name(self) -> &'static str;
+
+    /// Label shown in the command bar, shared by actions shown together.
+    fn label(self) -> &'static str;
+
+    /// Description shown in the help overlay.
+    fn description(self) -> &'static str;
+}
+
+/// A key action shared by the text input modes.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum InputAction {
+    Submit,
+    Cancel,
+    PopChar,
+    Delete,
+    DeleteWord,
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    Home,
+    End,
+}
+
+/// A key action in the command line.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CommandAction {
+    Submit,
+    Cancel,
+    Complete,
+    Older,
+    Newer,
+    PopChar,
+}
+
+/// A key action that records or replays a keyboard macro in Normal mode.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MacroAction {
+    Record,
+    Play,
+}
+
+/// Key bindings for the actions of a single mode.
+pub struct ModeKeymap<A> {
+    bindings: Vec<(Key, A)>,
+}
+
+/// Key bindings for every mode.
+pub struct Keymap {
+    pub load: ModeKeymap<LoadMsg>,
+    pub normal: ModeKeymap<NormalMsg>,
+    pub insert: ModeKeymap<InsertMsg>,
+    pub move_mode: ModeKeymap<MoveMsg>,
+    pub visual: ModeKeymap<VisualMsg>,
+    pub save: ModeKeymap<SaveMsg>,
+    pub paste: ModeKeymap<PasteMsg>,
+    pub input: ModeKeymap<InputAction>,
+    pub command: ModeKeymap<CommandAction>,
+    pub confirm: ModeKeymap<ConfirmMsg>,
+    pub macros: ModeKeymap<MacroAction>,
+    pub help: ModeKeymap<HelpMsg>,
+}
+
+/// One key or a list of keys bound to an action in the config file.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Key binding overrides for each mode, mapping action names to keys.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeysConfig {
+    pub load: BTreeMap<String, KeyList>,
+    pub normal: BTreeMap<String, KeyList>,
+    pub insert: BTreeMap<String, KeyList>,
+    #[serde(rename = "move")]
+    pub move_mode: BTreeMap<String, KeyList>,
+    pub visual: BTreeMap<String, KeyList>,
+    pub save: BTreeMap<String, KeyList>,
+    pub paste: BTreeMap<String, KeyList>,
+    pub input: BTreeMap<String, KeyList>,
+    pub command: BTreeMap<String, KeyList>,
+    pub confirm: BTreeMap<String, KeyList>,
+    #[serde(rename = "macro")]
+    pub macros: BTreeMap<String, KeyList>,
+    pub help: BTreeMap<String, KeyList>,
+}
+
+const LOAD_KEYS: &[(KeyCode, LoadMsg)] = &[
+    (KeyCode::Char('k'), LoadMsg::Decrement),
+    (KeyCode::Char('j'), LoadMsg::Increment),
+    (KeyCode::Char('n'), LoadMsg::New),
+    (KeyCode::Char('r'), LoadMsg::Rename),
+    (KeyCode::Char('d'), LoadMsg::Delete),
+    (KeyCode::Char('q'), LoadMsg::Quit),
+    (KeyCode::Down, LoadMsg::Increment),
+    (KeyCode::Up, LoadMsg::Decrement),
+    (KeyCode::Enter, LoadMsg::Open),
+];
+
+const NORMAL_KEYS: &[(KeyCode, NormalMsg)] = &[
+    (KeyCode::Char('h'), NormalMsg::Ascend),
+    (KeyCode::Char('l'), NormalMsg::Descend),
+    (KeyCode::Char('k'), NormalMsg::Previous),
+    (KeyCode::Char('j'), NormalMsg::Next),
+    (KeyCode::Char('K'), NormalMsg::FirstSibling),
+    (KeyCode::Char('J'), NormalMsg::LastSibling),
+    (KeyCode::Char('g'), NormalMsg::Top),
+    (KeyCode::Char('G'), NormalMsg::Bottom),
+    (KeyCode::Char('-'), NormalMsg::PrevVisible),
+    (KeyCode::Char('+'), NormalMsg::NextVisible),
+    (KeyCode::Char(')'), NormalMsg::ParentNext),
+    (KeyCode::Char('e'), NormalMsg::Edit),
+    (KeyCode::Char('i'), NormalMsg::Insert),
+    (KeyCode::Char('m'), NormalMsg::Move),
+    (KeyCode::Char('v'), NormalMsg::Visual),
+    (KeyCode::Char('n'), NormalMsg::Nest),
+    (KeyCode::Char('f'), NormalMsg::Flatten),
+    (KeyCode::Char('d'), NormalMsg::Delete),
+    (KeyCode::Char('p'), NormalMsg::Put),
+    (KeyCode::Char('u'), NormalMsg::Undo),
+    (KeyCode::Char('r'), NormalMsg::Redo),
+    (KeyCode::Char('{'), NormalMsg::Older),
+    (KeyCode::Char('}'), NormalMsg::Newer),
+    (KeyCode::Char('z'), NormalMsg::Fold),
+    (KeyCode::Char('>'), NormalMsg::Hoist),
+    (KeyCode::Char('<'), NormalMsg::Unhoist),
+    (KeyCode::Char('^'), NormalMsg::UnhoistAll),
+    (KeyCode::Char('/'), NormalMsg::Search),
+    (KeyCode::Char(']'), NormalMsg::NextMatch),
+    (KeyCode::Char('['), NormalMsg::PrevMatch),
+    (KeyCode::Char('M'), NormalMsg::SetMark),
+    (KeyCode::Char('\''), NormalMsg::JumpMark),
+    (KeyCode::Tab, NormalMsg::JumpForward),
+    (KeyCode::Char(':'), NormalMsg::CommandLine),
+    (KeyCode::Char('.'), NormalMsg::Repeat),
+    (KeyCode::Char('q'), NormalMsg::Quit),
+    (KeyCode::Left, NormalMsg::Ascend),
+    (KeyCode::Right, NormalMsg::Descend),
+    (KeyCode::Up, NormalMsg::Previous),
+    (KeyCode::Down, NormalMsg::Next),
+    (KeyCode::Home, NormalMsg::Top),
+    (KeyCode::End, NormalMsg::Bottom),
+    (KeyCode::PageUp, NormalMsg::PageUp),
+    (KeyCode::PageDown, NormalMsg::PageDown),
+];
+
+const NORMAL_MODIFIED_KEYS: &[(Key, NormalMsg)] = &[
+    (Key::ctrl('o'), NormalMsg::JumpBack),
+    (Key::ctrl('p'), NormalMsg::PrevVisible),
+    (Key::ctrl('n'), NormalMsg::NextVisible),
+    (Key::ctrl('b'), NormalMsg::PageUp),
+    (Key::ctrl('f'), NormalMsg::PageDown),
+];
+
+const INSERT_KEYS: &[(KeyCode, InsertMsg)] = &[
+    (KeyCode::Char('h'), InsertMsg::Parent),
+    (KeyCode::Char('l'), InsertMsg::Child),
+    (KeyCode::Char('k'), InsertMsg::Before),
+    (KeyCode::Char('j'), InsertMsg::After),
+    (KeyCode::Char(' '), InsertMsg::Cancel),
+];
+
+const MOVE_KEYS: &[(KeyCode, MoveMsg)] = &[
+    (KeyCode::Char('h'), MoveMsg::Promote),
+    (KeyCode::Char('l'), MoveMsg::Demote),
+    (KeyCode::Char('k'), MoveMsg::Backward),
+    (KeyCode::Char('j'), MoveMsg::Forward),
+    (KeyCode::Left, MoveMsg::Promote),
+    (KeyCode::Right, MoveMsg::Demote),
+    (KeyCode::Up, MoveMsg::Backward),
+    (KeyCode::Down, MoveMsg::Forward),
+    (KeyCode::Char(' '), MoveMsg::Done),
+];
+
+const VISUAL_KEYS: &[(KeyCode, VisualMsg)] = &[
+    (KeyCode::Char('k'), VisualMsg::Previous),
+    (KeyCode::Char('j'), VisualMsg::Next),
+    (KeyCode::Up, VisualMsg::Previous),
+    (KeyCode::Down, VisualMsg::Next),
+    (KeyCode::Char('K'), VisualMsg::Backward),
+    (KeyCode::Char('J'), VisualMsg::Forward),
+    (KeyCode::Char('<'), VisualMsg::Promote),
+    (KeyCode::Char('>'), VisualMsg::Demote),
+    (KeyCode::Char('w'), VisualMsg::Wrap),
+    (KeyCode::Char('y'), VisualMsg::Yank),
+    (KeyCode::Char('d'), VisualMsg::Delete),
+    (KeyCode::Char('s'), VisualMsg::Sort),
+    (KeyCode::Char('v'), VisualMsg::Cancel),
+    (KeyCode::Char(' '), VisualMsg::Cancel),
+];
+
+const SAVE_KEYS: &[(KeyCode, SaveMsg)] = &[
+    (KeyCode::Char('j'), SaveMsg::Toggle),
+    (KeyCode::Enter, SaveMsg::Confirm),
+    (KeyCode::Char(' '), SaveMsg::Cancel),
+];
+
+const PASTE_KEYS: &[(KeyCode, PasteMsg)] = &[
+    (KeyCode::Char('j'), PasteMsg::Toggle),
+    (KeyCode::Enter, PasteMsg::Confirm),
+    (KeyCode::Char(' '), PasteMsg::Cancel),
+];
+
+const INPUT_KEYS: &[(KeyCode, InputAction)] = &[
+    (KeyCode::Backspace, InputAction::PopChar),
+    (KeyCode::Delete, InputAction::Delete),
+    (KeyCode::Left, InputAction::Left),
+    (KeyCode::Right, InputAction::Right),
+    (KeyCode::Home, InputAction::Home),
+    (KeyCode::End, InputAction::End),
+    (KeyCode::Enter, InputAction::Submit),
+    (KeyCode::Tab, InputAction::Cancel),
+];
+
+const INPUT_MODIFIED_KEYS: &[(Key, InputAction)] = &[
+    (Key::ctrl('w'), InputAction::DeleteWord),
+    (Key::ctrl('a'), InputAction::Home),
+    (Key::ctrl('e'), InputAction::End),
+    (Key::alt('b'), InputAction::WordLeft),
+    (Key::alt('f'), InputAction::WordRight),
+];
+
+const COMMAND_KEYS: &[(KeyCode, CommandAction)] = &[
+    (KeyCode::Backspace, CommandAction::PopChar),
+    (KeyCode::Tab, CommandAction::Complete),
+    (KeyCode::Up, CommandAction::Older),
+    (KeyCode::Down, CommandAction::Newer),
+    (KeyCode::Enter, CommandAction::Submit),
+    (KeyCode::Esc, CommandAction::Cancel),
+];
+
+const CONFIRM_KEYS: &[(KeyCode, ConfirmMsg)] = &[
+    (KeyCode::Enter, ConfirmMsg::Confirm),
+    (KeyCode::Char(' '), ConfirmMsg::Cancel),
+];
+
+const MACRO_KEYS: &[(KeyCode, MacroAction)] = &[
+    (KeyCode::Char('Q'), MacroAction::Record),
+    (KeyCode::Char('@'), MacroAction::Play),
+];
+
+const HELP_KEYS: &[(KeyCode, HelpMsg)] = &[
+    (KeyCode::Char('?'), HelpMsg::Toggle),
+    (KeyCode::Char('q'), HelpMsg::Close),
+    (KeyCode::Esc, HelpMsg::Close),
+    (KeyCode::Char('k'), HelpMsg::Up),
+    (KeyCode::Char('j'), HelpMsg::Down),
+    (KeyCode::Up, HelpMsg::Up),
+    (KeyCode::Down, HelpMsg::Down),
+    (KeyCode::PageUp, HelpMsg::PageUp),
+    (KeyCode::PageDown, HelpMsg::PageDown),
+];
+
+static KEYMAP: OnceCell<Keymap> = OnceCell::new();
+
+impl Key {
+    // Create a character Key with the Ctrl modifier.
+    const fn ctrl(c: char) -> Self {
+        Self {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::CONTROL,
+        }
+    }
+
+    // Create a character Key with the Alt modifier.
+    const fn alt(c: char) -> Self {
+        Self {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::ALT,
+        }
+    }
+
+    /// Create a Key from a pressed key event.
+    pub fn from_event(event: KeyEvent) -> Self {
+        let modifiers = match event.code {
+            KeyCode::Char(_) | KeyCode::BackTab => {
+                event.modifiers - KeyModifiers::SHIFT
+            }
+            _ => event.modifiers,
+        };
+        Self {
+            code: event.code,
+            modifiers,
+        }
+    }
+
+    /// Parse a key such as `j`, `Enter`, `Space` or `Ctrl-o`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut name = text;
+        while let Some((prefix, rest)) = name.split_once('-') {
+            if rest.is_empty() {
+                break;
+            }
+            modifiers |= match prefix.to_lowercase().as_str() {
+                "c" | "ctrl" => KeyModifiers::CONTROL,
+                "a" | "alt" => KeyModifiers::ALT,
+                _ => return None,
+            };
+            name = rest;
+        }
+        let mut chars = name.chars();
+        let code = match (chars.next(), chars.next()) {
+            // Terminals report Ctrl with a letter as the lowercase letter.
+            (Some(c), None) if modifiers.contains(KeyModifiers::CONTROL) => {
+                KeyCode::Char(c.to_ascii_lowercase())
+            }
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match name.to_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "enter" => KeyCode::Enter,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" => KeyCode::Delete,
+                "esc" => KeyCode::Esc,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                lower => KeyCode::F(lower.strip_prefix('f')?.parse().ok()?),
+            },
+        };
+        Some(Self { code, modifiers })
+    }
+
+    /// Return the key as text that `parse` reads back, e.g. `Ctrl-o`.
+    pub fn config_name(&self) -> String {
+        let name = match self.code {
+            KeyCode::Char(' ') => String::from("Space"),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::Left => String::from("Left"),
+            KeyCode::Right => String::from("Right"),
+            KeyCode::Up => String::from("Up"),
+            KeyCode::Down => String::from("Down"),
+            KeyCode::Enter => String::from("Enter"),
+            KeyCode::Tab => String::from("Tab"),
+            KeyCode::BackTab => String::from("BackTab"),
+            KeyCode::Backspace => String::from("Backspace"),
+            KeyCode::Delete => String::from("Delete"),
+            KeyCode::Esc => String::from("Esc"),
+            KeyCode::Home => String::from("Home"),
+            KeyCode::End => String::from("End"),
+            KeyCode::PageUp => String::from("PageUp"),
+            KeyCode::PageDown => String::from("PageDown"),
+            KeyCode::F(n) => format!("F{n}"),
+            code => code.to_string(),
+        };
+        let mut prefix = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            prefix.push_str("Ctrl-");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            prefix.push_str("Alt-");
+        }
+        prefix + &name
+    }
+
+    /// Return the text shown for the key and whether it is a plain character.
+    pub fn display(&self) -> (String, bool) {
+        let (name, is_char) = match self.code {
+            KeyCode::Char(' ') => (String::from("Space"), false),
+            KeyCode::Char(c) if c.is_uppercase() => (format!("⇧{c}"), false),
+            KeyCode::Char(c) => (c.to_uppercase().to_string(), true),
+            KeyCode::Left => (String::from("←"), false),
+            KeyCode::Right => (String::from("→"), false),
+            KeyCode::Up => (String::from("↑"), false),
+            KeyCode::Down => (String::from("↓"), false),
+            KeyCode::BackTab => (String::from("⇧Tab"), false),
+            KeyCode::PageUp => (String::from("PgUp"), false),
+            KeyCode::PageDown => (String::from("PgDn"), false),
+            KeyCode::Delete => (String::from("Del"), false),
+            code => (code.to_string(), false),
+        };
+        let mut prefix = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            prefix.push_str("Ctrl-");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            prefix.push_str("Alt-");
+        }
+        if prefix.is_empty() {
+            (name, is_char)
+        } else {
+            (prefix + &name, false)
+        }
+    }
+}
+
+impl<A: Action> ModeKeymap<A> {
+    // Build the bindings from the unmodified `defaults` and the
+    // `modified_defaults`, replacing the keys of any action named in
+    // `overrides`, or return an error naming the invalid binding.
+    fn new(
+        mode: &str,
+        defaults: &[(KeyCode, A)],
+        modified_defaults: &[(Key, A)],
+        overrides: &BTreeMap<String, KeyList>,
+    ) -> Result<Self, String> {
+        let file = fs::CONFIG_FILE;
+        let mut bindings: Vec<(Key, A)> = defaults
+            .iter()
+            .map(|&(code, action)| {
+                let key = Key {
+                    code,
+                    modifiers: KeyModifiers::NONE,
+                };
+                (key, action)
+            })
+            .chain(modified_defaults.iter().copied())
+            .collect();
+        let mut overridden = Vec::new();
+        for (name, key_list) in overrides {
+            let action = *A::ALL
+                .iter()
+                .find(|action| action.name() == name)
+                .ok_or_else(|| {
+                    format!("{file}: Unknown action `{name}` in [keys.{mode}]")
+                })?;
+            bindings.retain(|&(_, bound)| bound != action);
+            let texts = match key_list {
+                KeyList::One(text) => std::slice::from_ref(text),
+                KeyList::Many(texts) => texts.as_slice(),
+            };
+            for text in texts {
+                let key = Key::parse(text).ok_or_else(|| {
+                    format!("{file}: Invalid key `{text}` in [keys.{mode}]")
+                })?;
+                if overridden.contains(&key) {
+                    return Err(format!(
+                        "{file}: Key `{text}` is bound twice in [keys.{mode}]"
+                    ));
+                }
+                overridden.push(key);
+                bindings.retain(|&(bound, _)| bound != key);
+                bindings.push((key, action));
+            }
+        }
+        Ok(Self { bindings })
+    }
+
+    /// Return the action bound to `key`, if any.
+    pub fn lookup(&self, key: Key) -> Option<A> {
+        self.bindings
+            .iter()
+            .find(|&&(bound, _)| bound == key)
+            .map(|&(_, action)| action)
+    }
+
+    /// Return the keys bound to `action`.
+    pub fn keys(&self, action: A) -> impl Iterator<Item = Key> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |&&(_, bound)| bound == action)
+            .map(|&(key, _)| key)
+    }
+}
+
+// Return an error if a macro key would hide a Normal mode binding, since
+// macro keys are looked up first.
+fn check_macro_keys(keymap: &Keymap) -> Result<(), String> {
+    for &(key, _) in &keymap.macros.bindings {
+        if keymap.normal.lookup(key).is_some() {
+            let name = key.config_name();
+            return Err(format!(
+                "{}: Key `{name}` is bound in both [keys.macro] and \
+                 [keys.normal]",
+                fs::CONFIG_FILE
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Build the keymap from the defaults and the user config.
+fn load() -> Result<Keymap, String> {
+    let keys = &config::get().keys;
+    let keymap = Keymap {
+        load: ModeKeymap::new("load", LOAD_KEYS, &[], &keys.load)?,
+        normal: ModeKeymap::new(
+            "normal",
+            NORMAL_KEYS,
+            NORMAL_MODIFIED_KEYS,
+            &keys.normal,
+        )?,
+        insert: ModeKeymap::new("insert", INSERT_KEYS, &[], &keys.insert)?,
+        move_mode: ModeKeymap::new("move", MOVE_KEYS, &[], &keys.move_mode)?,
+        visual: ModeKeymap::new("visual", VISUAL_KEYS, &[], &keys.visual)?,
+        save: ModeKeymap::new("save", SAVE_KEYS, &[], &keys.save)?,
+        paste: ModeKeymap::new("paste", PASTE_KEYS, &[], &keys.paste)?,
+        input: ModeKeymap::new(
+            "input",
+            INPUT_KEYS,
+            INPUT_MODIFIED_KEYS,
+            &keys.input,
+        )?,
+        command: ModeKeymap::new("command", COMMAND_KEYS, &[], &keys.command)?,
+        confirm: ModeKeymap::new("confirm", CONFIRM_KEYS, &[], &keys.confirm)?,
+        macros: ModeKeymap::new("macro", MACRO_KEYS, &[], &keys.macros)?,
+        help: ModeKeymap::new("help", HELP_KEYS, &[], &keys.help)?,
+    };
+    check_macro_keys(&keymap)?;
+    Ok(keymap)
+}
+
+/// Build the keymap from the config, or return an error naming the invalid
+/// binding. Must be called after the config is loaded and before `get`.
+pub fn init() -> Result<(), String> {
+    if KEYMAP.set(load()?).is_err() {
+        panic!("Keymap already loaded");
+    }
+    Ok(())
+}
+
+/// Return the keymap.
+pub fn get() -> &'static Keymap {
+    KEYMAP.get().expect("Keymap not loaded")
+}
+
+impl Action for LoadMsg {
+    const ALL: &'static [Self] = &[
+        Self::Decrement,
+        Self::Increment,
+        Self::Open,
+        Self::New,
+        Self::Rename,
+        Self::Delete,
+        Self::Quit,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Decrement => "decrement",
+            Self::Increment => "increment",
+            Self::Open => "open",
+            Self::New => "new",
+            Self::Rename => "rename",
+            Self::Delete => "delete",
+            Self::Quit => "quit",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Decrement | Self::Increment => "Navigate",
+            Self::Open => "Open",
+            Self::New => "New",
+            Self::Rename => "Rename",
+            Self::Delete => "Delete",
+            Self::Quit => "Quit",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Decrement => "Select the previous file",
+            Self::Increment => "Select the next file",
+            Self::Open => "Open the selected file",
+            Self::New => "Start a new session",
+            Self::Rename => "Rename the selected file",
+            Self::Delete => "Delete the selected file",
+            Self::Quit => "Quit",
+        }
+    }
+}
+
+impl Action for NormalMsg {
+    const ALL: &'static [Self] = &[
+        Self::Ascend,
+        Self::Descend,
+        Self::Previous,
+        Self::Next,
+        Self::FirstSibling,
+        Self::LastSibling,
+        Self::Top,
+        Self::Bottom,
+        Self::PrevVisible,
+        Self::NextVisible,
+        Self::ParentNext,
+        Self::PageUp,
+        Self::PageDown,
+        Self::Edit,
+        Self::Insert,
+        Self::Move,
+        Self::Visual,
+        Self::Nest,
+        Self::Flatten,
+        Self::Delete,
+        Self::Put,
+        Self::Undo,
+        Self::Redo,
+        Self::Older,
+        Self::Newer,
+        Self::Fold,
+        Self::Hoist,
+        Self::Unhoist,
+        Self::UnhoistAll,
+        Self::Search,
+        Self::NextMatch,
+        Self::PrevMatch,
+        Self::SetMark,
+        Self::JumpMark,
+        Self::JumpBack,
+        Self::JumpForward,
+        Self::CommandLine,
+        Self::Repeat,
+        Self::Quit,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Ascend => "ascend",
+            Self::Descend => "descend",
+            Self::Previous => "previous",
+            Self::Next => "next",
+            Self::FirstSibling => "first_sibling",
+            Self::LastSibling => "last_sibling",
+            Self::Top => "top",
+            Self::Bottom => "bottom",
+            Self::PrevVisible => "prev_visible",
+            Self::NextVisible => "next_visible",
+            Self::ParentNext => "parent_next",
+            Self::PageUp => "page_up",
+            Self::PageDown => "page_down",
+            Self::Edit => "edit",
+            Self::Insert => "insert",
+            Self::Move => "move",
+            Self::Visual => "visual",
+            Self::Nest => "nest",
+            Self::Flatten => "flatten",
+            Self::Delete => "delete",
+            Self::Put => "put",
+            Self::Undo => "undo",
+            Self::Redo => "redo",
+            Self::Older => "older",
+            Self::Newer => "newer",
+            Self::Fold => "fold",
+            Self::Hoist => "hoist",
+            Self::Unhoist => "unhoist",
+            Self::UnhoistAll => "unhoist_all",
+            Self::Search => "search",
+            Self::NextMatch => "next_match",
+            Self::PrevMatch => "prev_match",
+            Self::SetMark => "set_mark",
+            Self::JumpMark => "jump_mark",
+            Self::JumpBack => "jump_back",
+            Self::JumpForward => "jump_forward",
+            Self::CommandLine => "command_line",
+            Self::Repeat => "repeat",
+            Self::Quit => "quit",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Ascend | Self::Descend | Self::Previous | Self::Next => {
+                "Navigate"
+            }
+            Self::FirstSibling | Self::LastSibling => "Ends",
+            Self::Top | Self::Bottom => "Top/Bottom",
+            Self::PrevVisible | Self::NextVisible => "Line",
+            Self::ParentNext => "Parent Next",
+            Self::PageUp | Self::PageDown => "Page",
+            Self::Edit => "Edit",
+            Self::Insert => "Insert",
+            Self::Move => "Move",
+            Self::Visual => "Select",
+            Self::Nest => "Nest",
+            Self::Flatten => "Flatten",
+            Self::Delete => "Delete",
+            Self::Put => "Put",
+            Self::Undo => "Undo",
+            Self::Redo => "Redo",
+            Self::Older | Self::Newer => "History",
+            Self::Fold => "Fold",
+            Self::Hoist => "Hoist",
+            Self::Unhoist | Self::UnhoistAll => "Unhoist",
+            Self::Search => "Search",
+            Self::NextMatch | Self::PrevMatch => "Match",
+            Self::SetMark => "Mark",
+            Self::JumpMark => "Jump",
+            Self::JumpBack | Self::JumpForward => "Jumps",
+            Self::CommandLine => "Command",
+            Self::Repeat => "Repeat",
+            Self::Quit => "Quit",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Ascend => "Focus the parent",
+            Self::Descend => "Focus the first child",
+            Self::Previous => "Focus the previous sibling",
+            Self::Next => "Focus the next sibling",
+            Self::FirstSibling => "Focus the first sibling",
+            Self::LastSibling => "Focus the last sibling",
+            Self::Top => "Focus the top of the forest",
+            Self::Bottom => "Focus the bottom of the forest",
+            Self::PrevVisible => "Focus the previous line",
+            Self::NextVisible => "Focus the next line",
+            Self::ParentNext => "Focus the parent's next sibling",
+            Self::PageUp => "Move up a screen",
+            Self::PageDown => "Move down a screen",
+            Self::Edit => "Rename the focused item",
+            Self::Insert => "Insert a new item",
+            Self::Move => "Move the focused subtree",
+            Self::Visual => "Select a range of siblings",
+            Self::Nest => "Nest the following siblings under the item",
+            Self::Flatten => "Move the children after the item",
+            Self::Delete => "Delete the focused item",
+            Self::Put => "Put the yanked items after the item",
+            Self::Undo => "Undo the last change",
+            Self::Redo => "Redo the last undone change",
+            Self::Older => "Go to the previous state in time",
+            Self::Newer => "Go to the next state in time",
+            Self::Fold => "Fold or unfold the children",
+            Self::Hoist => "Show only the focused subtree",
+            Self::Unhoist => "Show the hoisted item's parent subtree",
+            Self::UnhoistAll => "Show the whole forest",
+            Self::Search => "Search item labels",
+            Self::NextMatch => "Focus the next match",
+            Self::PrevMatch => "Focus the previous match",
+            Self::SetMark => "Mark the focused item",
+            Self::JumpMark => "Jump to a mark",
+            Self::JumpBack => "Go back in the jump list",
+            Self::JumpForward => "Go forward in the jump list",
+            Self::CommandLine => "Enter a command",
+            Self::Repeat => "Repeat the last edit",
+            Self::Quit => "Close the file",
+        }
+    }
+}
+
+impl Action for InsertMsg {
+    const ALL: &'static [Self] = &[
+        Self::Parent,
+        Self::Child,
+        Self::Before,
+        Self::After,
+        Self::Cancel,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Parent => "parent",
+            Self::Child => "child",
+            Self::Before => "before",
+            Self::After => "after",
+            Self::Cancel => "cancel",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Parent => "Parent",
+            Self::Child => "Child",
+            Self::Before => "Before",
+            Self::After => "After",
+            Self::Cancel => "Cancel",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Parent => "Insert a new parent",
+            Self::Child => "Insert a new first child",
+            Self::Before => "Insert before the item",
+            Self::After => "Insert after the item",
+            Self::Cancel => "Cancel",
+        }
+    }
+}
+
+impl Action for MoveMsg {
+    const ALL: &'static [Self] = &[
+        Self::Promote,
+        Self::Demote,
+        Self::Backward,
+        Self::Forward,
+        Self::Done,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Promote => "promote",
+            Self::Demote => "demote",
+            Self::Backward => "backward",
+            Self::Forward => "forward",
+            Self::Done => "done",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Promote => "Promote",
+            Self::Demote => "Demote",
+            Self::Backward => "Up",
+            Self::Forward => "Down",
+            Self::Done => "Done",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Promote => "Move after the parent",
+            Self::Demote => "Move into the next sibling",
+            Self::Backward => "Swap with the previous sibling",
+            Self::Forward => "Swap with the next sibling",
+            Self::Done => "Finish moving",
+        }
+    }
+}
+
+impl Action for VisualMsg {
+    const ALL: &'static [Self] = &[
+        Self::Previous,
+        Self::Next,
+        Self::Backward,
+        Self::Forward,
+        Self::Promote,
+        Self::Demote,
+        Self::Wrap,
+        Self::Yank,
+        Self::Delete,
+        Self::Sort,
+        Self::Cancel,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Previous => "previous",
+            Self::Next => "next",
+            Self::Backward => "backward",
+            Self::Forward => "forward",
+            Self::Promote => "promote",
+            Self::Demote => "demote",
+            Self::Wrap => "wrap",
+            Self::Yank => "yank",
+            Self::Delete => "delete",
+            Self::Sort => "sort",
+            Self::Cancel => "cancel",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Previous | Self::Next => "Select",
+            Self::Backward | Self::Forward => "Move",
+            Self::Promote => "Promote",
+            Self::Demote => "Demote",
+            Self::Wrap => "Wrap",
+            Self::Yank => "Yank",
+            Self::Delete => "Delete",
+            Self::Sort => "Sort",
+            Self::Cancel => "Cancel",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Previous => "Extend to the previous sibling",
+            Self::Next => "Extend to the next sibling",
+            Self::Backward => "Move the selection backward",
+            Self::Forward => "Move the selection forward",
+            Self::Promote => "Move the selection after the parent",
+            Self::Demote => "Move the selection into the next sibling",
+            Self::Wrap => "Wrap the selection in a new parent",
+            Self::Yank => "Copy the selection",
+            Self::Delete => "Cut the selection",
+            Self::Sort => "Sort the selection",
+            Self::Cancel => "Cancel",
+        }
+    }
+}
+
+impl Action for SaveMsg {
+    const ALL: &'static [Self] = &[Self::Toggle, Self::Confirm, Self::Cancel];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Toggle => "toggle",
+            Self::Confirm => "confirm",
+            Self::Cancel => "cancel",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Toggle => "Toggle",
+            Self::Confirm => "Confirm",
+            Self::Cancel => "Cancel",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Toggle => "Switch between saving and discarding",
+            Self::Confirm => "Confirm the choice",
+            Self::Cancel => "Return to the file",
+        }
+    }
+}
+
+impl Action for PasteMsg {
+    const ALL: &'static [Self] = &[Self::Toggle, Self::Confirm, Self::Cancel];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Toggle => "toggle",
+            Self::Confirm => "confirm",
+            Self::Cancel => "cancel",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Toggle => "Toggle",
+            Self::Confirm => "Confirm",
+            Self::Cancel => "Cancel",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Toggle => "Switch between items and outline",
+            Self::Confirm => "Insert the pasted lines",
+            Self::Cancel => "Cancel",
+        }
+    }
+}
+
+impl Action for InputAction {
+    const ALL: &'static [Self] = &[
+        Self::Submit,
+        Self::Cancel,
+        Self::PopChar,
+        Self::Delete,
+        Self::DeleteWord,
+        Self::Left,
+        Self::Right,
+        Self::WordLeft,
+        Self::WordRight,
+        Self::Home,
+        Self::End,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Submit => "submit",
+            Self::Cancel => "cancel",
+            Self::PopChar => "backspace",
+            Self::Delete => "delete",
+            Self::DeleteWord => "delete_word",
+            Self::Left => "left",
+            Self::Right => "right",
+            Self::WordLeft => "word_left",
+            Self::WordRight => "word_right",
+            Self::Home => "home",
+            Self::End => "end",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Submit => "Submit",
+            Self::Cancel => "Cancel",
+            Self::PopChar => "Backspace",
+            Self::Delete | Self::DeleteWord => "Delete",
+            Self::Left | Self::Right | Self::Home | Self::End => "Cursor",
+            Self::WordLeft | Self::WordRight => "Word",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Submit => "Submit the text",
+            Self::Cancel => "Cancel",
+            Self::PopChar => "Delete the previous character",
+            Self::Delete => "Delete the character under the cursor",
+            Self::DeleteWord => "Delete the previous word",
+            Self::Left => "Move the cursor left",
+            Self::Right => "Move the cursor right",
+            Self::WordLeft => "Move the cursor a word left",
+            Self::WordRight => "Move the cursor a word right",
+            Self::Home => "Move the cursor to the start",
+            Self::End => "Move the cursor to the end",
+        }
+    }
+}
+
+impl Action for CommandAction {
+    const ALL: &'static [Self] = &[
+        Self::Submit,
+        Self::Cancel,
+        Self::Complete,
+        Self::Older,
+        Self::Newer,
+        Self::PopChar,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Submit => "submit",
+            Self::Cancel => "cancel",
+            Self::Complete => "complete",
+            Self::Older => "older",
+            Self::Newer => "newer",
+            Self::PopChar => "backspace",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Submit => "Run",
+            Self::Cancel => "Cancel",
+            Self::Complete => "Complete",
+            Self::Older | Self::Newer => "History",
+            Self::PopChar => "Backspace",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Submit => "Run the command",
+            Self::Cancel => "Cancel",
+            Self::Complete => "Complete the command",
+            Self::Older => "Recall an older command",
+            Self::Newer => "Recall a newer command",
+            Self::PopChar => "Delete the previous character",
+        }
+    }
+}
+
+impl Action for ConfirmMsg {
+    const ALL: &'static [Self] = &[Self::Confirm, Self::Cancel];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Confirm => "confirm",
+            Self::Cancel => "cancel",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Confirm => "Confirm",
+            Self::Cancel => "Cancel",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Confirm => "Confirm",
+            Self::Cancel => "Cancel",
+        }
+    }
+}
+
+impl Action for MacroAction {
+    const ALL: &'static [Self] = &[Self::Record, Self::Play];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Record => "record",
+            Self::Play => "play",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Record => "Record",
+            Self::Play => "Play",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Record => "Record keys into a register, or stop",
+            Self::Play => "Replay the keys in a register",
+        }
+    }
+}
+
+impl Action for HelpMsg {
+    const ALL: &'static [Self] = &[
+        Self::Toggle,
+        Self::Close,
+        Self::Up,
+        Self::Down,
+        Self::PageUp,
+        Self::PageDown,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Toggle => "toggle",
+            Self::Close => "close",
+            Self::Up => "up",
+            Self::Down => "down",
+            Self::PageUp => "page_up",
+            Self::PageDown => "page_down",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Toggle => "Help",
+            Self::Close => "Close",
+            Self::Up | Self::Down => "Scroll",
+            Self::PageUp | Self::PageDown => "Page",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Toggle => "Open or close the help",
+            Self::Close => "Close the help",
+            Self::Up => "Scroll up",
+            Self::Down => "Scroll down",
+            Self::PageUp => "Scroll up a page",
+            Self::PageDown => "Scroll down a page",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Option<String> {
+        Key::parse(text).map(|key| key.config_name())
+    }
+
+    #[test]
+    fn parse_chars() {
+        assert_eq!(parse("j").as_deref(), Some("j"));
+        assert_eq!(parse("J").as_deref(), Some("J"));
+        assert_eq!(parse("-").as_deref(), Some("-"));
+        assert_eq!(parse("é").as_deref(), Some("é"));
+        assert_eq!(parse("Space").as_deref(), Some("Space"));
+        assert_eq!(parse("").as_deref(), None);
+    }
+
+    #[test]
+    fn parse_modifiers() {
+        assert_eq!(parse("Ctrl-o").as_deref(), Some("Ctrl-o"));
+        assert_eq!(parse("Ctrl-O").as_deref(), Some("Ctrl-o"));
+        assert_eq!(parse("C-O").as_deref(), Some("Ctrl-o"));
+        assert_eq!(parse("ctrl-alt-x").as_deref(), Some("Ctrl-Alt-x"));
+        assert_eq!(parse("A-J").as_deref(), Some("Alt-J"));
+        assert_eq!(parse("Ctrl--").as_deref(), Some("Ctrl--"));
+        assert_eq!(parse("Ctrl-Enter").as_deref(), Some("Ctrl-Enter"));
+        assert_eq!(parse("Shift-x"), None);
+        assert_eq!(parse("Ctrl-"), None);
+    }
+
+    #[test]
+    fn parse_named() {
+        assert_eq!(parse("enter").as_deref(), Some("Enter"));
+        assert_eq!(parse("PAGEDOWN").as_deref(), Some("PageDown"));
+        assert_eq!(parse("BackTab").as_deref(), Some("BackTab"));
+        assert_eq!(parse("F12").as_deref(), Some("F12"));
+        assert_eq!(parse("f1").as_deref(), Some("F1"));
+        assert_eq!(parse("F").as_deref(), Some("F"));
+        assert_eq!(parse("F1x"), None);
+        assert_eq!(parse("Fx"), None);
+        assert_eq!(parse("Return"), None);
+    }
+
+    fn normal(
+        overrides: &[(&str, &[&str])],
+    ) -> Result<ModeKeymap<NormalMsg>, String> {
+        let overrides = overrides
+            .iter()
+            .map(|(name, texts)| {
+                let texts = texts.iter().map(|text| text.to_string()).collect();
+                (name.to_string(), KeyList::Many(texts))
+            })
+            .collect();
+        ModeKeymap::new("normal", NORMAL_KEYS, NORMAL_MODIFIED_KEYS, &overrides)
+    }
+
+    fn error(overrides: &[(&str, &[&str])]) -> String {
+        match normal(overrides) {
+            Ok(_) => panic!("Expected the bindings to fail"),
+            Err(error) => error,
+        }
+    }
+
+    #[test]
+    fn override_bindings() {
+        let keymap = normal(&[("undo", &["Ctrl-U"])]).unwrap();
+        let undo = Key::parse("Ctrl-u").unwrap();
+        assert!(keymap.lookup(undo) == Some(NormalMsg::Undo));
+        assert_eq!(keymap.keys(NormalMsg::Undo).count(), 1);
+    }
+
+    #[test]
+    fn override_errors() {
+        assert_eq!(
+            error(&[("frobnicate", &["x"])]),
+            "config.toml: Unknown action `frobnicate` in [keys.normal]"
+        );
+        assert_eq!(
+            error(&[("undo", &["Hyper-x"])]),
+            "config.toml: Invalid key `Hyper-x` in [keys.normal]"
+        );
+        assert_eq!(
+            error(&[("undo", &["x", "x"])]),
+            "config.toml: Key `x` is bound twice in [keys.normal]"
+        );
+        assert_eq!(
+            error(&[("redo", &["x"]), ("undo", &["x"])]),
+            "config.toml: Key `x` is bound twice in [keys.normal]"
+        );
+    }
+
+    #[test]
+    fn parse_matches_events() {
+        let event =
+            |code, modifiers| Key::from_event(KeyEvent::new(code, modifiers));
+        let shift_j = event(KeyCode::Char('J'), KeyModifiers::SHIFT);
+        assert!(Key::parse("J") == Some(shift_j));
+        let ctrl_o = event(KeyCode::Char('o'), KeyModifiers::CONTROL);
+        assert!(Key::parse("Ctrl-O") == Some(ctrl_o));
+        assert!(Key::parse("Ctrl-o") == Some(Key::ctrl('o')));
+        assert!(Key::parse("Alt-x") == Some(Key::alt('x')));
+    }
+}
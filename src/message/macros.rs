@@ -0,0 +1,313 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use crossterm::event::{KeyCode, KeyModifiers};
+
+use crate::{
+    config,
+    io::fs::{self, MACROS_FILE},
+    message::{
+        Message, key_to_message,
+        keymap::{self, Key, MacroAction},
+    },
+    model::Model,
+};
+
+// Most keys replayed for one macro the user typed, including the macros it
+// replays, stopping macros that replay themselves.
+const MAX_PLAYED: usize = 10_000;
+
+// Macro action waiting for the register named by the next key.
+enum Pending {
+    Record,
+    Play(usize),
+}
+
+/// Keyboard macros recorded into registers, and the keys queued to replay.
+#[derive(Default)]
+pub struct Macros {
+    registers: HashMap<char, Vec<Key>>,
+    recording: Option<(char, Vec<Key>)>,
+    pending: Option<Pending>,
+    queue: VecDeque<Key>,
+    played: usize,
+}
+
+// Return the register named by a `key`, if it is a letter or digit.
+fn to_register(key: Key) -> Option<char> {
+    match key {
+        Key {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::NONE,
+        } if c.is_alphanumeric() => Some(c),
+        _ => None,
+    }
+}
+
+// Parse the registers saved in the macros file `text`, skipping any that
+// cannot be read with a warning in `warnings`.
+fn parse_registers(
+    text: &str,
+    warnings: &mut Vec<String>,
+) -> HashMap<char, Vec<Key>> {
+    let saved: BTreeMap<String, Vec<String>> =
+        match config::parse_toml(text, MACROS_FILE) {
+            Ok(saved) => saved,
+            Err(error) => {
+                warnings.push(format!("{error}, macros not loaded"));
+                return HashMap::new();
+            }
+        };
+    let mut registers = HashMap::new();
+    for (name, texts) in saved {
+        let mut chars = name.chars();
+        let register = match (chars.next(), chars.next()) {
+            (Some(c), None) => c,
+            _ => {
+                warnings.push(format!(
+                    "{MACROS_FILE}: Invalid register `{name}`, skipped"
+                ));
+                continue;
+            }
+        };
+        let keys: Option<Vec<Key>> =
+            texts.iter().map(|text| Key::parse(text)).collect();
+        match keys {
+            Some(keys) => {
+                registers.insert(register, keys);
+            }
+            None => warnings.push(format!(
+                "{MACROS_FILE}: Invalid key in register `{name}`, skipped"
+            )),
+        }
+    }
+    registers
+}
+
+// Show the `alert` in a Normal mode `model`.
+fn with_alert(model: Model, alert: String) -> Model {
+    match model {
+        Model::Normal(state) => Model::Normal(state.set_alert(alert)),
+        model => model,
+    }
+}
+
+impl Macros {
+    /// Create the macros, loading the saved registers if they persist, with
+    /// a warning for each register that could not be read.
+    pub fn load() -> (Self, Vec<String>) {
+        let mut warnings = Vec::new();
+        let registers = match fs::read_macros() {
+            Some(text) if config::get().macros.persist => {
+                parse_registers(&text, &mut warnings)
+            }
+            _ => HashMap::new(),
+        };
+        let macros = Self {
+            registers,
+            ..Self::default()
+        };
+        (macros, warnings)
+    }
+
+    /// Return the next key queued for playback, if any.
+    pub fn next_key(&mut self) -> Option<Key> {
+        self.queue.pop_front()
+    }
+
+    // Write the registers to the macros file if they persist.
+    fn save(&self) -> std::io::Result<()> {
+        if !config::get().macros.persist {
+            return Ok(());
+        }
+        let saved: BTreeMap<String, Vec<String>> = self
+            .registers
+            .iter()
+            .map(|(register, keys)| {
+                let texts = keys.iter().map(Key::config_name).collect();
+                (register.to_string(), texts)
+            })
+            .collect();
+        let text = toml::to_string(&saved).expect("Failed to serialize macros");
+        fs::write_macros(&text)
+    }
+
+    // Stop recording into the register, or wait for one to record into.
+    // Return an alert if the registers could not be saved.
+    fn toggle_recording(&mut self) -> Option<String> {
+        match self.recording.take() {
+            Some((register, mut keys)) => {
+                // Drop the key that stopped the recording.
+                keys.pop();
+                self.registers.insert(register, keys);
+                let error = self.save().err()?;
+                Some(format!("Failed to save macros: {error}"))
+            }
+            None => {
+                self.pending = Some(Pending::Record);
+                None
+            }
+        }
+    }
+
+    // Queue the keys of the `register` to play `count` times before any keys
+    // already queued, or stop playback and return false if that would replay
+    // more than `MAX_PLAYED` keys since the user last typed a key.
+    fn play(&mut self, register: char, count: usize) -> bool {
+        let Some(keys) = self.registers.get(&register) else {
+            return true;
+        };
+        let total = keys.len().saturating_mul(count);
+        self.played = self.played.saturating_add(total);
+        if self.played > MAX_PLAYED {
+            self.queue.clear();
+            return false;
+        }
+        let played: Vec<Key> =
+            keys.iter().copied().cycle().take(total).collect();
+        for key in played.into_iter().rev() {
+            self.queue.push_front(key);
+        }
+        true
+    }
+
+    // Show the register being recorded into in a Normal mode `model`.
+    fn show(&self, mut model: Model) -> Model {
+        if let Model::Normal(state) = &mut model {
+            state.recording = self.recording.as_ref().map(|&(c, _)| c);
+        }
+        model
+    }
+
+    /// Map a `key` to a Message, handling the macro keys in Normal mode and
+    /// recording the key if it was `typed` rather than replayed.
+    pub fn key_to_message(
+        &mut self,
+        model: Model,
+        key: Key,
+        typed: bool,
+    ) -> Message {
+        if typed {
+            // Keys are only read from the terminal once the queue is empty,
+            // so a typed key starts a new count of replayed keys.
+            self.played = 0;
+        }
+        if let (true, Some((_, keys))) = (typed, &mut self.recording) {
+            keys.push(key);
+        }
+        if let Some(pending) = self.pending.take() {
+            match (pending, to_register(key)) {
+                (Pending::Record, Some(register)) => {
+                    self.recording = Some((register, Vec::new()));
+                }
+                (Pending::Play(count), Some(register)) => {
+                    if !self.play(register, count) {
+                        let alert =
+                            String::from("Macro stopped: too many keys");
+                        return Message::Continue(with_alert(
+                            self.show(model),
+                            alert,
+                        ));
+                    }
+                }
+                (_, None) => {}
+            }
+            return Message::Continue(self.show(model));
+        }
+        let action = match &model {
+            Model::Normal(_) => keymap::get().macros.lookup(key),
+            _ => None,
+        };
+        match (action, model) {
+            (Some(MacroAction::Record), model) => {
+                let model = match self.toggle_recording() {
+                    Some(alert) => with_alert(self.show(model), alert),
+                    None => self.show(model),
+                };
+                Message::Continue(model)
+            }
+            (Some(MacroAction::Play), Model::Normal(mut state)) => {
+                let count = state.take_count();
+                self.pending = Some(Pending::Play(count));
+                Message::Continue(Model::Normal(state.clear_alert()))
+            }
+            (_, model) => key_to_message(self.show(model), key),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(texts: &[&str]) -> Vec<Key> {
+        texts.iter().map(|text| Key::parse(text).unwrap()).collect()
+    }
+
+    #[test]
+    fn parse_saved_registers() {
+        let text = "a = [\"j\", \"Ctrl-o\"]\nbb = [\"k\"]\nc = [\"Hyper-x\"]\n";
+        let mut warnings = Vec::new();
+        let registers = parse_registers(text, &mut warnings);
+        assert_eq!(registers.len(), 1);
+        let names: Vec<String> =
+            registers[&'a'].iter().map(Key::config_name).collect();
+        assert_eq!(names, ["j", "Ctrl-o"]);
+        assert_eq!(
+            warnings,
+            [
+                "macros.toml: Invalid register `bb`, skipped",
+                "macros.toml: Invalid key in register `c`, skipped",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_corrupt_registers() {
+        let mut warnings = Vec::new();
+        assert!(parse_registers("a = [", &mut warnings).is_empty());
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("macros.toml line 1: "));
+        assert!(warnings[0].ends_with(", macros not loaded"));
+    }
+
+    #[test]
+    fn play_count() {
+        let mut macros = Macros::default();
+        macros.registers.insert('a', keys(&["j", "k"]));
+        macros.queue.extend(keys(&["x"]));
+        assert!(macros.play('a', 2));
+        let queued: Vec<String> = std::iter::from_fn(|| macros.next_key())
+            .map(|key| key.config_name())
+            .collect();
+        assert_eq!(queued, ["j", "k", "j", "k", "x"]);
+        assert!(macros.play('b', 1));
+    }
+
+    #[test]
+    fn macro_replaying_itself_stops() {
+        let mut macros = Macros::default();
+        macros.registers.insert('a', keys(&["j", "@", "a"]));
+        assert!(macros.play('a', 1));
+        // Replay the keys, playing the register after each `@`.
+        let mut replayed = 0;
+        let mut after_play = false;
+        while let Some(key) = macros.next_key() {
+            replayed += 1;
+            assert!(replayed <= MAX_PLAYED);
+            if after_play && !macros.play('a', 1) {
+                break;
+            }
+            after_play = key.config_name() == "@";
+        }
+        assert!(macros.queue.is_empty());
+        assert!(macros.played > MAX_PLAYED);
+    }
+
+    #[test]
+    fn large_count_stops() {
+        let mut macros = Macros::default();
+        macros.registers.insert('a', keys(&["j"]));
+        assert!(!macros.play('a', usize::MAX));
+        assert!(macros.next_key().is_none());
+    }
+}
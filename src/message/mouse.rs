@@ -0,0 +1,106 @@
+use std::time::{Duration, Instant};
+
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+
+use crate::{
+    message::{HelpMsg, LoadMsg, Message, NormalMsg},
+    model::{LoadState, Model, SessionState},
+    view::{ClickMap, ClickTarget},
+};
+
+// Longest delay between the clicks of a double-click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// The most recent click, used to detect double-clicks.
+#[derive(Default)]
+pub struct MouseState {
+    last_click: Option<(Instant, ClickTarget)>,
+}
+
+impl MouseState {
+    // Record a click on the `target`, returning whether it completes a
+    // double-click.
+    fn click(&mut self, target: &ClickTarget) -> bool {
+        let now = Instant::now();
+        let is_double = self.last_click.as_ref().is_some_and(|(time, last)| {
+            last == target && now.duration_since(*time) <= DOUBLE_CLICK
+        });
+        self.last_click = if is_double {
+            None
+        } else {
+            Some((now, target.clone()))
+        };
+        is_double
+    }
+}
+
+// Map a click on the `target` to a Message in Load mode.
+fn to_load_msg(
+    target: &ClickTarget,
+    double: bool,
+    state: LoadState,
+) -> Message {
+    match *target {
+        ClickTarget::File(_) if double => Message::Load(LoadMsg::Open, state),
+        ClickTarget::File(index) => Message::SelectFile(index, state),
+        ClickTarget::Node(_) => Message::Continue(Model::Load(state)),
+    }
+}
+
+// Map a click on the `target` to a Message in Normal mode.
+fn to_normal_msg(
+    target: &ClickTarget,
+    double: bool,
+    state: SessionState,
+) -> Message {
+    let state = state.clear_alert().clear_count();
+    match target {
+        ClickTarget::Node(_) if double && !state.read_only => {
+            Message::Normal(NormalMsg::Edit, state)
+        }
+        ClickTarget::Node(path) => Message::FocusNode(path.clone(), state),
+        ClickTarget::File(_) => Message::Continue(Model::Normal(state)),
+    }
+}
+
+/// Convert a mouse `event` into a Message, resolving its position against the
+/// items rendered in the last frame.
+pub fn to_message(
+    model: Model,
+    event: MouseEvent,
+    click_map: &ClickMap,
+    mouse_state: &mut MouseState,
+) -> Message {
+    let model = match (model, event.kind) {
+        (Model::Help(state), MouseEventKind::ScrollDown) => {
+            return Message::Help(HelpMsg::Down, state);
+        }
+        (Model::Help(state), MouseEventKind::ScrollUp) => {
+            return Message::Help(HelpMsg::Up, state);
+        }
+        (model, _) => model,
+    };
+    let (target, double) = match event.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            match click_map.at_row(event.row) {
+                Some(target) => (target, mouse_state.click(target)),
+                None => return Message::Continue(model),
+            }
+        }
+        MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => {
+            let forward = event.kind == MouseEventKind::ScrollDown;
+            match click_map.adjacent(forward) {
+                Some(target) => (target, false),
+                None => return Message::Continue(model),
+            }
+        }
+        _ => return Message::Continue(model),
+    };
+    match model {
+        Model::Load(load_state) => to_load_msg(target, double, load_state),
+        Model::Normal(session_state) => {
+            to_normal_msg(target, double, session_state)
+        }
+        model => Message::Continue(model),
+    }
+}
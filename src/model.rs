@@ -1,6 +1,25 @@
-use std::{fs::File, path::PathBuf};
+pub mod help;
+pub mod history;
+pub mod jump_list;
+pub mod text_input;
 
-use crate::zipper::FocusNode;
+use std::{collections::BTreeMap, fs::File, path::PathBuf, time::Duration};
+
+use crate::{
+    config,
+    io::data::DataFile,
+    message::{InsertMsg, MoveMsg, NormalMsg, VisualMsg},
+    model::{
+        history::{ChangeKind, Checkpoint, History, SavedHistory},
+        jump_list::JumpList,
+        text_input::TextInput,
+    },
+    outline,
+    zipper::{FocusNode, Hoist, Tree},
+};
+
+const MAX_COUNT: usize = 9999;
+const MAX_COMPLETIONS: usize = 8;
 
 /// The `name` and `path` of a file.
 pub struct FileEntry {
@@ -30,12 +49,40 @@ pub struct ForestState {
     pub changed: bool,
 }
 
+/// A step of an edit, recorded so the edit can be repeated.
+#[derive(Clone)]
+pub enum EditStep {
+    Normal(NormalMsg),
+    Insert(InsertMsg),
+    Move(MoveMsg),
+    Visual(VisualMsg, usize),
+    Label(String),
+    Paste(String, bool),
+    Confirm,
+}
+
 /// Persistent state for an active session.
+///
+/// While a subtree is hoisted, the forest holds only that subtree and the
+/// hoist holds the nodes around it.
 pub struct SessionState {
     pub forest: ForestState,
-    pub undo_stack: Vec<ForestState>,
-    pub redo_stack: Vec<ForestState>,
+    pub hoist: Option<Hoist>,
+    pub history: History,
     pub maybe_file: Option<OpenDataFile>,
+    pub read_only: bool,
+    pub search: Option<String>,
+    pub count: Option<usize>,
+    pub command_history: Vec<String>,
+    pub alert: Option<String>,
+    pub marks: BTreeMap<char, u64>,
+    pub marks_changed: bool,
+    pub jumps: JumpList,
+    pub yank: Vec<Tree>,
+    pub page_height: usize,
+    pub recording: Option<char>,
+    pub pending_edit: Vec<EditStep>,
+    pub last_edit: Vec<EditStep>,
 }
 
 /// User's current save choice and subsequent action.
@@ -53,11 +100,19 @@ pub enum LabelAction {
 /// Current user input label and action to be performed with it.
 pub struct LabelState {
     fallback: ForestState,
-    pub input: String,
+    pub input: TextInput,
     pub action: LabelAction,
     pub session: SessionState,
 }
 
+/// Multi-line text pasted during label input, and whether to insert it as an
+/// outline using its indentation rather than one item per line.
+pub struct PasteState {
+    pub as_outline: bool,
+    pub text: String,
+    pub label_state: LabelState,
+}
+
 /// Status of the user input filename string.
 pub enum FilenameStatus {
     Empty,
@@ -69,20 +124,60 @@ pub enum FilenameStatus {
 /// Action to perform with the user input filename string.
 pub enum FilenameAction {
     Rename(LoadState),
-    SaveNew(SessionState),
+    SaveNew(Box<SessionState>),
 }
 
 /// Current user input filename with status and next action to be performed.
 pub struct FilenameState {
-    pub input: String,
+    pub input: TextInput,
     pub status: FilenameStatus,
     pub action: FilenameAction,
 }
 
+/// Current user input search pattern.
+pub struct SearchState {
+    pub input: TextInput,
+    pub session: SessionState,
+}
+
+/// Current command line input, its completions and the history position.
+pub struct CommandLineState {
+    pub input: TextInput,
+    pub completions: Vec<&'static str>,
+    history_index: Option<usize>,
+    pub session: SessionState,
+}
+
+/// Siblings selected in Visual mode, from the `anchor` sibling index to the
+/// focused node.
+pub struct VisualState {
+    pub anchor: usize,
+    pub session: SessionState,
+}
+
+/// Sibling indices from `start` to `end` under the node at the `parent` path.
+pub struct SiblingRange {
+    pub parent: Vec<usize>,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Whether a mark is being set on the focused node or jumped to.
+pub enum MarkAction {
+    Set,
+    Jump,
+}
+
+/// Mark action waiting for the letter naming the mark.
+pub struct MarkState {
+    pub action: MarkAction,
+    pub session: SessionState,
+}
+
 /// Action to be confirmed in Confirm mode.
 pub enum ConfirmState {
     NewSession,
-    DeleteItem(SessionState),
+    DeleteItem(Box<SessionState>),
     DeleteFile(LoadState),
 }
 
@@ -94,10 +189,26 @@ pub enum Model {
     Move(SessionState),
     Save(SaveState),
     LabelInput(LabelState),
+    Paste(PasteState),
+    Visual(VisualState),
     FilenameInput(FilenameState),
+    Search(SearchState),
+    CommandLine(CommandLineState),
+    Mark(MarkState),
+    Help(help::HelpState),
     Confirm(ConfirmState),
 }
 
+// Return whether `label` contains `pattern`, ignoring case unless the
+// pattern contains an uppercase character.
+fn matches_pattern(label: &str, pattern: &str) -> bool {
+    if pattern.chars().any(char::is_uppercase) {
+        label.contains(pattern)
+    } else {
+        label.to_lowercase().contains(pattern)
+    }
+}
+
 impl LoadState {
     /// Extract the selected FileEntry, consuming the instance.
     pub fn extract_selected(mut self) -> FileEntry {
@@ -116,6 +227,15 @@ impl LoadState {
         }
     }
 
+    /// Select the file at the `index`, if it exists.
+    pub fn select(self, index: usize) -> Self {
+        if index < self.files.len() {
+            Self { index, ..self }
+        } else {
+            self
+        }
+    }
+
     /// Increment the `index`.
     pub fn increment(self) -> Self {
         if self.index + 1 == self.files.len() {
@@ -134,7 +254,7 @@ impl LabelState {
     pub fn new_rename(label: String, session: SessionState) -> Self {
         Self {
             fallback: session.forest.clone(),
-            input: label,
+            input: TextInput::from(label),
             action: LabelAction::Rename,
             session,
         }
@@ -149,7 +269,7 @@ impl LabelState {
         };
         Self {
             fallback,
-            input: String::new(),
+            input: TextInput::default(),
             action: LabelAction::Insert,
             session,
         }
@@ -159,26 +279,12 @@ impl LabelState {
     pub fn new_insert(session: SessionState, fallback: ForestState) -> Self {
         Self {
             fallback,
-            input: String::new(),
+            input: TextInput::default(),
             action: LabelAction::Insert,
             session,
         }
     }
 
-    /// Append a character to the input string, not starting with whitespace.
-    pub fn append(mut self, c: char) -> Self {
-        if !(self.input.is_empty() && c == ' ') {
-            self.input.push(c);
-        }
-        self
-    }
-
-    /// Pop a character from the input text.
-    pub fn pop(mut self) -> Self {
-        self.input.pop();
-        self
-    }
-
     /// Set the label of the focused node to the trimmed user input.
     pub fn set_label(self) -> SessionState {
         let Self {
@@ -187,7 +293,7 @@ impl LabelState {
             mut session,
             ..
         } = self;
-        let label = input.trim().to_string();
+        let label = input.as_str().trim().to_string();
         let focus = session.forest.focus.map(|focus| focus.set_label(label));
         session.forest = ForestState {
             focus,
@@ -197,6 +303,32 @@ impl LabelState {
         session
     }
 
+    /// Insert the `trees` at the focused node, replacing it if it is a new
+    /// item without a label, and otherwise following it.
+    pub fn insert_trees(self, trees: Vec<Tree>) -> SessionState {
+        let Self {
+            fallback,
+            input,
+            action,
+            mut session,
+        } = self;
+        let label = input.as_str().trim().to_string();
+        let focus = session.forest.focus.map(|focus| {
+            let focus = match action {
+                LabelAction::Insert if label.is_empty() => focus,
+                _ if label.is_empty() => focus.insert_next(),
+                _ => focus.set_label(label).insert_next(),
+            };
+            focus.replace_with_trees(trees)
+        });
+        session.forest = ForestState {
+            focus,
+            changed: true,
+        };
+        session.push_history(fallback);
+        session
+    }
+
     /// Fallback to the previous state.
     pub fn fallback(self) -> SessionState {
         SessionState {
@@ -206,11 +338,92 @@ impl LabelState {
     }
 }
 
+impl PasteState {
+    pub fn new(text: String, label_state: LabelState) -> Self {
+        Self {
+            as_outline: false,
+            text,
+            label_state,
+        }
+    }
+
+    /// Toggle between inserting one item per line and an outline.
+    pub fn toggle(mut self) -> Self {
+        self.as_outline = !self.as_outline;
+        self
+    }
+
+    /// Parse the pasted text into trees, returning them with the label state.
+    pub fn into_trees(self) -> (Vec<Tree>, LabelState) {
+        let trees = if self.as_outline {
+            outline::parse_indented(&self.text)
+        } else {
+            outline::parse_lines(&self.text)
+        };
+        (trees, self.label_state)
+    }
+}
+
+impl VisualState {
+    /// Select the focused node and up to `len - 1` following siblings,
+    /// focusing on the first of them.
+    pub fn new(session: SessionState, len: usize) -> Self {
+        let (index, following) = session
+            .focus()
+            .map(|focus| (focus.path().pop().unwrap_or(0), focus.count_next()))
+            .unwrap_or_default();
+        Self {
+            anchor: index + len.saturating_sub(1).min(following),
+            session,
+        }
+    }
+
+    /// Return the selected range of siblings.
+    pub fn range(&self) -> SiblingRange {
+        let mut parent = self
+            .session
+            .focus()
+            .map(FocusNode::path)
+            .unwrap_or_default();
+        let index = parent.pop().unwrap_or(0);
+        SiblingRange {
+            parent,
+            start: index.min(self.anchor),
+            end: index.max(self.anchor),
+        }
+    }
+}
+
+impl SiblingRange {
+    /// Return the number of selected siblings.
+    pub fn len(&self) -> usize {
+        self.end - self.start + 1
+    }
+
+    /// Return the path of the first selected sibling.
+    pub fn start_path(&self) -> Vec<usize> {
+        let mut path = self.parent.clone();
+        path.push(self.start);
+        path
+    }
+
+    /// Return whether the node at `path` is in one of the selected subtrees.
+    pub fn contains(&self, path: &[usize]) -> bool {
+        match path.get(self.parent.len()) {
+            Some(index) => {
+                path.starts_with(&self.parent)
+                    && (self.start..=self.end).contains(index)
+            }
+            None => false,
+        }
+    }
+}
+
 impl FilenameState {
     /// Create a FilenameState to rename a file.
     pub fn new_rename(load_state: LoadState) -> Self {
         Self {
-            input: String::new(),
+            input: TextInput::default(),
             status: FilenameStatus::Empty,
             action: FilenameAction::Rename(load_state),
         }
@@ -219,24 +432,10 @@ impl FilenameState {
     /// Create a FilenameState to save a new file.
     pub fn new_save(session: SessionState) -> Self {
         Self {
-            input: String::new(),
+            input: TextInput::default(),
             status: FilenameStatus::Empty,
-            action: FilenameAction::SaveNew(session),
-        }
-    }
-
-    /// Append a character to the input string, not starting with whitespace.
-    pub fn append(mut self, c: char) -> Self {
-        if !(self.input.is_empty() && c == ' ') {
-            self.input.push(c);
+            action: FilenameAction::SaveNew(Box::new(session)),
         }
-        self
-    }
-
-    /// Pop a character from the input text.
-    pub fn pop(mut self) -> Self {
-        self.input.pop();
-        self
     }
 
     /// Set the filename status.
@@ -252,7 +451,112 @@ impl FilenameState {
 
     /// Return a reference to the trimmed user input.
     pub fn trimmed(&self) -> &str {
-        self.input.trim()
+        self.input.as_str().trim()
+    }
+}
+
+impl SearchState {
+    pub fn new(session: SessionState) -> Self {
+        Self {
+            input: TextInput::default(),
+            session,
+        }
+    }
+
+    /// Store the input as the search pattern and focus on its next match.
+    pub fn submit(self) -> SessionState {
+        let Self { input, mut session } = self;
+        session.search = Some(input.as_str().to_string());
+        session.find_match(true)
+    }
+}
+
+impl CommandLineState {
+    pub fn new(session: SessionState) -> Self {
+        Self {
+            input: TextInput::default(),
+            completions: Vec::new(),
+            history_index: None,
+            session,
+        }
+    }
+
+    /// Complete the word before the cursor from the `candidates`.
+    ///
+    /// A unique candidate is completed in full, otherwise the input is
+    /// extended to the candidates' common prefix and they are listed.
+    pub fn complete(mut self, candidates: Vec<&'static str>) -> Self {
+        let word_start =
+            self.input.before_cursor().rfind(' ').map_or(0, |i| i + 1);
+        match candidates.as_slice() {
+            [] => (),
+            [candidate] => {
+                let completed = format!("{candidate} ");
+                self.input.replace_before_cursor(word_start, &completed);
+            }
+            [first, rest @ ..] => {
+                let common = rest.iter().fold(*first, |common, candidate| {
+                    let len = common
+                        .chars()
+                        .zip(candidate.chars())
+                        .take_while(|(a, b)| a == b)
+                        .map(|(a, _)| a.len_utf8())
+                        .sum();
+                    &common[..len]
+                });
+                self.input.replace_before_cursor(word_start, common);
+            }
+        }
+        self.completions = candidates;
+        self.completions.truncate(MAX_COMPLETIONS);
+        if self.completions.len() == 1 {
+            self.completions.clear();
+        }
+        self
+    }
+
+    /// Replace the input with an older entry from the history.
+    pub fn older(mut self) -> Self {
+        let history = &self.session.command_history;
+        let index = match self.history_index {
+            Some(index) => index.saturating_sub(1),
+            None if history.is_empty() => return self,
+            None => history.len() - 1,
+        };
+        self.input = TextInput::from(history[index].clone());
+        self.history_index = Some(index);
+        self.completions.clear();
+        self
+    }
+
+    /// Replace the input with a newer entry from the history.
+    pub fn newer(mut self) -> Self {
+        let history = &self.session.command_history;
+        match self.history_index {
+            Some(index) if index + 1 < history.len() => {
+                self.input = TextInput::from(history[index + 1].clone());
+                self.history_index = Some(index + 1);
+            }
+            Some(_) => {
+                self.input = TextInput::default();
+                self.history_index = None;
+            }
+            None => (),
+        }
+        self.completions.clear();
+        self
+    }
+
+    /// Record the input in the history and return it with the session.
+    pub fn submit(self) -> (String, SessionState) {
+        let Self {
+            input, mut session, ..
+        } = self;
+        let input = input.as_str().trim().to_string();
+        if session.command_history.last() != Some(&input) {
+            session.command_history.push(input.clone());
+        }
+        (input, session)
     }
 }
 
@@ -279,15 +583,212 @@ impl SessionState {
                 focus: None,
                 changed: false,
             },
-            undo_stack: Vec::new(),
-            redo_stack: Vec::new(),
+            hoist: None,
+            history: History::new(config::get().history.limit),
             maybe_file: None,
+            read_only: false,
+            search: None,
+            count: None,
+            command_history: Vec::new(),
+            alert: None,
+            marks: BTreeMap::new(),
+            marks_changed: false,
+            jumps: JumpList::default(),
+            yank: Vec::new(),
+            page_height: 0,
+            recording: None,
+            pending_edit: Vec::new(),
+            last_edit: Vec::new(),
+        }
+    }
+
+    /// Create a read-only SessionState to view the forest.
+    pub fn new_read_only(focus: Option<FocusNode>) -> Self {
+        Self {
+            forest: ForestState {
+                focus,
+                changed: false,
+            },
+            read_only: true,
+            ..Self::new()
+        }
+    }
+
+    /// Append a `digit` to the pending count.
+    pub fn push_digit(mut self, digit: usize) -> Self {
+        let count = self.count.unwrap_or(0).saturating_mul(10) + digit;
+        self.count = Some(count.min(MAX_COUNT));
+        self
+    }
+
+    /// Show an `alert` in the status bar until the next key press.
+    pub fn set_alert(mut self, alert: impl Into<String>) -> Self {
+        self.alert = Some(alert.into());
+        self
+    }
+
+    /// Clear the alert shown in the status bar.
+    pub fn clear_alert(mut self) -> Self {
+        self.alert = None;
+        self
+    }
+
+    /// Clear the pending count.
+    pub fn clear_count(mut self) -> Self {
+        self.count = None;
+        self
+    }
+
+    /// Take the pending count, defaulting to one.
+    pub fn take_count(&mut self) -> usize {
+        self.count.take().unwrap_or(1)
+    }
+
+    // Return the `forest` of the session with the nodes around a hoisted
+    // subtree put back.
+    fn whole(&self, forest: ForestState) -> ForestState {
+        match &self.hoist {
+            Some(hoist) => ForestState {
+                focus: hoist.clone().attach(forest.focus),
+                changed: forest.changed,
+            },
+            None => forest,
         }
     }
 
-    fn push_history(&mut self, forest: ForestState) {
-        self.undo_stack.push(forest);
-        self.redo_stack.clear();
+    /// Return the whole forest, including the nodes hidden by a hoist.
+    pub fn whole_forest(&self) -> ForestState {
+        self.whole(self.forest.clone())
+    }
+
+    // Record a change from the `before` forest to the live forest.
+    fn push_history(&mut self, before: ForestState) {
+        self.record_change(before, None);
+    }
+
+    // Record a change from the `before` forest to the live forest, merged
+    // with a directly preceding change of the same `kind` if it has one, and
+    // end the hoist if the hoisted subtree was deleted.
+    fn record_change(&mut self, before: ForestState, kind: Option<ChangeKind>) {
+        let before = self.whole(before);
+        let after = self.whole_forest();
+        match kind {
+            Some(kind) => self.history.merge(kind, before, &after),
+            None => self.history.push(before, &after),
+        }
+        if self.forest.focus.is_none() && self.hoist.take().is_some() {
+            self.forest = after;
+        }
+    }
+
+    /// Replace the changes recorded since the history `checkpoint` with a
+    /// single change from the `before` forest to the live forest.
+    pub fn squash_history(
+        mut self,
+        checkpoint: Checkpoint,
+        before: ForestState,
+    ) -> Self {
+        let before = self.whole(before);
+        let after = self.whole_forest();
+        self.history.squash(checkpoint, before, &after);
+        self
+    }
+
+    // Hoist the subtree of the focused node's ancestor at `depth` in the
+    // whole forest, or show the whole forest if there is no depth.
+    fn hoist_at(mut self, depth: Option<usize>) -> Self {
+        let ForestState { focus, changed } = self.whole_forest();
+        self.hoist = None;
+        let focus = match (focus, depth) {
+            (Some(focus), Some(depth)) => {
+                let (focus, hoist) = focus.hoist(depth);
+                self.hoist = Some(hoist);
+                Some(focus)
+            }
+            (focus, _) => focus,
+        };
+        self.forest = ForestState { focus, changed };
+        self
+    }
+
+    /// Show only the focused node's subtree, as if it were the whole forest.
+    pub fn hoist(self) -> Self {
+        let Some(focus) = self.focus() else {
+            return self;
+        };
+        let hidden = self.hoist.as_ref().map_or(0, Hoist::depth);
+        let depth = hidden + focus.depth();
+        self.hoist_at(Some(depth))
+    }
+
+    /// Show the subtree of the hoisted node's parent, or the whole forest if
+    /// it has none.
+    pub fn unhoist(self) -> Self {
+        match self.hoist.as_ref().map(Hoist::depth) {
+            Some(depth) => self.hoist_at(depth.checked_sub(1)),
+            None => self,
+        }
+    }
+
+    /// Show the whole forest.
+    pub fn unhoist_all(self) -> Self {
+        match self.hoist {
+            Some(_) => self.hoist_at(None),
+            None => self,
+        }
+    }
+
+    // Go to another state of the history with `f`, keeping the hoist if the
+    // node then focused is within the hoisted subtree.
+    fn travel<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut History, ForestState) -> ForestState,
+    {
+        let forest = self.whole_forest();
+        self.forest = f(&mut self.history, forest);
+        let hoist = self.hoist.take();
+        self.rehoist(hoist)
+    }
+
+    // Focus on the node at `path` in the whole forest, keeping the hoist if
+    // the node is within the hoisted subtree.
+    fn focus_whole_path(mut self, path: &[usize]) -> Self {
+        let ForestState { focus, changed } = self.whole_forest();
+        let focus = focus.map(|focus| focus.focus_path(path));
+        self.forest = ForestState { focus, changed };
+        let hoist = self.hoist.take();
+        self.rehoist(hoist)
+    }
+
+    // Hoist the node of a previous `hoist` again, now that the live forest is
+    // the whole forest, if it is an ancestor of the focused node.
+    fn rehoist(self, hoist: Option<Hoist>) -> Self {
+        let hoisted = match (self.forest.focus.as_ref(), hoist) {
+            (Some(focus), Some(hoist)) => focus.ancestor_depth(hoist.id()),
+            _ => None,
+        };
+        match hoisted {
+            Some(depth) => self.hoist_at(Some(depth)),
+            None => self,
+        }
+    }
+
+    /// Start recording an edit with its first `step`.
+    pub fn begin_edit(mut self, step: EditStep) -> Self {
+        self.pending_edit = vec![step];
+        self
+    }
+
+    /// Record the next `step` of the edit in progress.
+    pub fn record_edit(mut self, step: EditStep) -> Self {
+        self.pending_edit.push(step);
+        self
+    }
+
+    /// Store the edit in progress as the last edit.
+    pub fn finish_edit(mut self) -> Self {
+        self.last_edit = std::mem::take(&mut self.pending_edit);
+        self
     }
 
     /// Apply a navigation function to the focused node.
@@ -299,6 +800,92 @@ impl SessionState {
         self
     }
 
+    /// Apply a navigation function to the focused node, remembering the
+    /// current node in the jump list.
+    pub fn jump<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(FocusNode) -> FocusNode,
+    {
+        if let Some(id) = self.focus().map(FocusNode::id) {
+            self.jumps.push(id);
+        }
+        self.navigate(f)
+    }
+
+    /// Focus on the node at the `path`, remembering the current node in the
+    /// jump list.
+    pub fn jump_to_path(self, path: &[usize]) -> Self {
+        self.jump(|focus| focus.focus_path(path))
+    }
+
+    /// Return to the node focused before the last jump, `count` times.
+    pub fn jump_back(self, count: usize) -> Self {
+        (0..count).fold(self, |state, _| state.follow_jump(true))
+    }
+
+    /// Return to the node focused after the next jump, `count` times.
+    pub fn jump_forward(self, count: usize) -> Self {
+        (0..count).fold(self, |state, _| state.follow_jump(false))
+    }
+
+    // Focus on the next node of the jump list going `back` or forward.
+    fn follow_jump(mut self, back: bool) -> Self {
+        let Some(current) = self.focus().map(FocusNode::id) else {
+            return self;
+        };
+        let whole = self.whole_forest();
+        let Some(focus) = whole.focus.as_ref() else {
+            return self;
+        };
+        let find = |id| focus.find_id(id);
+        let maybe_path = match back {
+            true => self.jumps.back(current, find),
+            false => self.jumps.forward(current, find),
+        };
+        match maybe_path {
+            Some(path) => self.focus_whole_path(&path),
+            None => self,
+        }
+    }
+
+    /// Mark the focused node with the letter `mark`.
+    pub fn set_mark(mut self, mark: char) -> Self {
+        if let Some(id) = self.focus().map(FocusNode::id) {
+            self.marks.insert(mark, id);
+            self.marks_changed = true;
+        }
+        self
+    }
+
+    /// Focus on the node marked with the letter `mark`, if it still exists.
+    pub fn jump_to_mark(mut self, mark: char) -> Self {
+        let whole = self.whole_forest();
+        let maybe_path = match (self.marks.get(&mark), whole.focus.as_ref()) {
+            (Some(&id), Some(focus)) => focus.find_id(id),
+            _ => None,
+        };
+        let Some(path) = maybe_path else {
+            return self.set_alert(format!("Mark {mark} not found"));
+        };
+        if let Some(id) = self.focus().map(FocusNode::id) {
+            self.jumps.push(id);
+        }
+        self.focus_whole_path(&path)
+    }
+
+    /// Focus on the next or previous node matching the search pattern.
+    pub fn find_match(self, forward: bool) -> Self {
+        let maybe_path = match (&self.search, self.focus()) {
+            (Some(pattern), Some(focus)) => focus
+                .find_label(|label| matches_pattern(label, pattern), forward),
+            _ => None,
+        };
+        match maybe_path {
+            Some(path) => self.jump_to_path(&path),
+            None => self,
+        }
+    }
+
     /// Apply a node insertion function and mark the state as changed.
     pub fn insert<F>(mut self, f: F) -> Self
     where
@@ -310,7 +897,25 @@ impl SessionState {
     }
 
     /// Apply a function to the focused node and update the history.
-    pub fn map_focus<F>(mut self, f: F) -> Self
+    pub fn map_focus<F>(self, f: F) -> Self
+    where
+        F: FnOnce(FocusNode) -> FocusNode,
+    {
+        self.change_focus(None, f)
+    }
+
+    /// Apply a function to the focused node as a change of the `kind`, merged
+    /// in the history with a directly preceding change of the same kind.
+    pub fn merge_focus<F>(self, kind: ChangeKind, f: F) -> Self
+    where
+        F: FnOnce(FocusNode) -> FocusNode,
+    {
+        self.change_focus(Some(kind), f)
+    }
+
+    // Apply a function to the focused node and record the change, merging it
+    // if it has a `kind`.
+    fn change_focus<F>(mut self, kind: Option<ChangeKind>, f: F) -> Self
     where
         F: FnOnce(FocusNode) -> FocusNode,
     {
@@ -318,33 +923,132 @@ impl SessionState {
         self.forest.focus = self.forest.focus.map(f);
         if self.forest.focus != old_forest.focus {
             self.forest.changed = true;
-            self.push_history(old_forest);
+            self.record_change(old_forest, kind);
         }
         self
     }
 
+    /// Apply a function to the siblings in the `range`, gathered under a
+    /// temporary parent, as a single change focused on the first of them,
+    /// merged with a preceding change of the same `kind` if it has one.
+    pub fn map_range<F>(
+        self,
+        range: &SiblingRange,
+        kind: Option<ChangeKind>,
+        f: F,
+    ) -> Self
+    where
+        F: FnOnce(FocusNode) -> FocusNode,
+    {
+        let len = range.len();
+        self.navigate(|focus| focus.focus_path(&range.start_path()))
+            .change_focus(kind, |focus| f(focus.wrap_range(len)).dissolve())
+    }
+
+    /// Record the next change as a new undo step.
+    pub fn end_merge(mut self) -> Self {
+        self.history.end_merge();
+        self
+    }
+
+    /// Copy the siblings in the `range` to the yank register.
+    pub fn yank_range(mut self, range: &SiblingRange) -> Self {
+        let start = self
+            .focus()
+            .map(|focus| focus.clone().focus_path(&range.start_path()));
+        if let Some(start) = start {
+            self.yank = start.range_to_trees(range.len());
+        }
+        let count = self.yank.len();
+        self.set_alert(format!("{count} yanked"))
+    }
+
+    /// Move the siblings in the `range` to the yank register, deleting them
+    /// with their subtrees.
+    pub fn delete_range(self, range: &SiblingRange) -> Self {
+        let mut state = self.yank_range(range).clear_alert();
+        let before = state.forest.clone();
+        let len = range.len();
+        state.forest.focus = state.forest.focus.and_then(|focus| {
+            focus
+                .focus_path(&range.start_path())
+                .wrap_range(len)
+                .delete_subtree()
+        });
+        state.forest.changed = true;
+        state.push_history(before);
+        state
+    }
+
+    /// Insert the trees in the yank register after the focused node.
+    pub fn put(mut self) -> Self {
+        if self.yank.is_empty() {
+            return self;
+        }
+        let trees = self.yank.clone();
+        match self.forest.focus {
+            Some(_) => self.map_focus(|focus| {
+                focus.insert_next().replace_with_trees(trees)
+            }),
+            None => {
+                let before = std::mem::replace(
+                    &mut self.forest,
+                    ForestState {
+                        focus: FocusNode::from_trees(trees),
+                        changed: true,
+                    },
+                );
+                self.push_history(before);
+                self
+            }
+        }
+    }
+
     /// Delete the focused node and mark the state as changed.
     pub fn delete(mut self) -> Self {
-        self.push_history(self.forest.clone());
+        let before = self.forest.clone();
         self.forest.focus = self.forest.focus.and_then(FocusNode::delete);
         self.forest.changed = true;
+        self.push_history(before);
         self
     }
 
-    pub fn undo(mut self) -> Self {
-        if let Some(prev_forest) = self.undo_stack.pop() {
-            self.redo_stack.push(self.forest);
-            self.forest = prev_forest;
-        }
-        self
+    /// Undo the last `count` changes.
+    pub fn undo_n(self, count: usize) -> Self {
+        (0..count).fold(self, |state, _| state.undo())
     }
 
-    pub fn redo(mut self) -> Self {
-        if let Some(next_forest) = self.redo_stack.pop() {
-            self.undo_stack.push(self.forest);
-            self.forest = next_forest;
-        }
-        self
+    /// Redo the last `count` undone changes.
+    pub fn redo_n(self, count: usize) -> Self {
+        (0..count).fold(self, |state, _| state.redo())
+    }
+
+    pub fn undo(self) -> Self {
+        self.travel(History::undo)
+    }
+
+    pub fn redo(self) -> Self {
+        self.travel(History::redo)
+    }
+
+    /// Go to the state `count` changes older in time, across undo branches.
+    pub fn older(self, count: usize) -> Self {
+        self.travel(|history, forest| history.older(count, forest))
+    }
+
+    /// Go to the state `count` changes newer in time, across undo branches.
+    pub fn newer(self, count: usize) -> Self {
+        self.travel(|history, forest| history.newer(count, forest))
+    }
+
+    /// Go to the state as it was the `duration` before the current one.
+    pub fn earlier(self, duration: Duration) -> Self {
+        self.travel(|history, forest| history.earlier(duration, forest))
+    }
+
+    /// Go to the state as it was the `duration` after the current one.
+    pub fn later(self, duration: Duration) -> Self {
+        self.travel(|history, forest| history.later(duration, forest))
     }
 
     pub fn is_empty(&self) -> bool {
@@ -352,7 +1056,7 @@ impl SessionState {
     }
 
     pub fn is_changed(&self) -> bool {
-        self.forest.changed
+        self.forest.changed || self.marks_changed
     }
 
     pub fn focus(&self) -> Option<&FocusNode> {
@@ -368,14 +1072,28 @@ impl SessionState {
         self.maybe_file.as_ref().map(|file| file.name.as_str())
     }
 
-    /// Return the forest and data file path (if present) from the session state.
+    /// Consume the session, returning the data to save and its open data
+    /// file, if any.
     ///
-    /// The locked File is implicitly dropped to unlock it.
-    pub fn unlock_state(self) -> (Option<FocusNode>, Option<PathBuf>) {
+    /// The file stays locked until the returned OpenDataFile is dropped, so
+    /// it can be written without another process taking the lock.
+    pub fn into_data_file(self) -> (DataFile, Option<OpenDataFile>) {
+        let forest = self.whole_forest();
+        let history = self.saved_history();
         let Self {
-            forest, maybe_file, ..
+            maybe_file, marks, ..
         } = self;
-        let maybe_path = maybe_file.map(|open_file| open_file.path);
-        (forest.focus, maybe_path)
+        let data = DataFile {
+            focus: forest.focus,
+            marks,
+            history,
+        };
+        (data, maybe_file)
+    }
+
+    /// Return the undo history to save with the forest, bounded by the
+    /// config.
+    pub fn saved_history(&self) -> Option<SavedHistory> {
+        self.history.save(config::get().history.keep)
     }
 }
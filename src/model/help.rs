@@ -0,0 +1,98 @@
+use crate::{
+    message::keymap::{self, Action, ModeKeymap},
+    model::Model,
+};
+
+/// A line of the help overlay describing an action and its keys.
+pub struct HelpEntry {
+    pub keys: String,
+    pub description: &'static str,
+    pub name: &'static str,
+}
+
+/// The help overlay listing the keys of the mode it was opened from, which
+/// is restored when it closes.
+pub struct HelpState {
+    pub title: &'static str,
+    pub entries: Vec<HelpEntry>,
+    pub scroll: usize,
+    pub page_height: usize,
+    pub model: Box<Model>,
+}
+
+// Return an entry for every action in the `keymap`, including unbound ones.
+fn to_entries<A: Action>(keymap: &ModeKeymap<A>) -> Vec<HelpEntry> {
+    A::ALL
+        .iter()
+        .map(|&action| {
+            let keys: Vec<String> =
+                keymap.keys(action).map(|key| key.display().0).collect();
+            HelpEntry {
+                keys: keys.join(" "),
+                description: action.description(),
+                name: action.name(),
+            }
+        })
+        .collect()
+}
+
+/// Return the title and entries for the mode of the `model`, if it has keys
+/// that do not enter text.
+pub fn mode_entries(model: &Model) -> Option<(&'static str, Vec<HelpEntry>)> {
+    let keymap = keymap::get();
+    let help = match model {
+        Model::Load(_) => ("Open", to_entries(&keymap.load)),
+        Model::Normal(_) => {
+            let mut entries = to_entries(&keymap.normal);
+            entries.extend(to_entries(&keymap.macros));
+            ("Normal", entries)
+        }
+        Model::Insert(_) => ("Insert", to_entries(&keymap.insert)),
+        Model::Move(_) => ("Move", to_entries(&keymap.move_mode)),
+        Model::Visual(_) => ("Visual", to_entries(&keymap.visual)),
+        Model::Save(_) => ("Save", to_entries(&keymap.save)),
+        Model::Paste(_) => ("Paste", to_entries(&keymap.paste)),
+        Model::Confirm(_) => ("Confirm", to_entries(&keymap.confirm)),
+        _ => return None,
+    };
+    Some(help)
+}
+
+impl HelpState {
+    /// Open the help with the `title` and `entries` over the `model`.
+    pub fn new(
+        title: &'static str,
+        entries: Vec<HelpEntry>,
+        model: Model,
+    ) -> Self {
+        Self {
+            title,
+            entries,
+            scroll: 0,
+            page_height: 0,
+            model: Box::new(model),
+        }
+    }
+
+    // Return the largest scroll offset that still fills the page.
+    fn max_scroll(&self) -> usize {
+        self.entries.len().saturating_sub(self.page_height)
+    }
+
+    /// Scroll up by `rows`.
+    pub fn scroll_up(mut self, rows: usize) -> Self {
+        self.scroll = self.scroll.min(self.max_scroll()).saturating_sub(rows);
+        self
+    }
+
+    /// Scroll down by `rows`, stopping at the last page.
+    pub fn scroll_down(mut self, rows: usize) -> Self {
+        self.scroll = (self.scroll + rows).min(self.max_scroll());
+        self
+    }
+
+    /// Close the help, returning to the previous mode.
+    pub fn close(self) -> Model {
+        *self.model
+    }
+}
@@ -0,0 +1,576 @@
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    model::ForestState,
+    zipper::{FocusNode, pool::ForestPool},
+};
+
+// A state in the undo tree, with the index of the child that redo returns to
+// and the paths focused before and after the change that led to it.
+struct HistoryNode {
+    forest: ForestState,
+    parent: Option<usize>,
+    redo_child: Option<usize>,
+    time: SystemTime,
+    before_path: Vec<usize>,
+    after_path: Vec<usize>,
+}
+
+// A state of a saved undo tree, whose forest is stored in the pool.
+#[derive(Serialize, Deserialize)]
+struct SavedNode {
+    parent: Option<usize>,
+    redo_child: Option<usize>,
+    time: SystemTime,
+    before_path: Vec<usize>,
+    after_path: Vec<usize>,
+}
+
+// Return the path of the node focused in the `forest`.
+fn focus_path(forest: &ForestState) -> Vec<usize> {
+    forest.focus.as_ref().map_or_else(Vec::new, FocusNode::path)
+}
+
+/// The latest states of an undo tree as saved in a data file, without the
+/// forest of the current state, which is saved as the file's forest.
+#[derive(Serialize, Deserialize)]
+pub struct SavedHistory {
+    forests: ForestPool,
+    nodes: Vec<SavedNode>,
+    current: usize,
+}
+
+/// The current state and the number of states when a group of changes
+/// began, for squashing them into one.
+#[derive(Clone, Copy)]
+pub struct Checkpoint {
+    position: usize,
+    len: usize,
+}
+
+/// Kinds of change that are merged into a single undo step when repeated.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Promote,
+    Demote,
+    Backward,
+    Forward,
+}
+
+/// Branching undo history of forest snapshots, indexed in the order they
+/// were created.
+///
+/// The snapshot of the current state is only brought up to date when it is
+/// left, since the session holds the live forest. The oldest states are
+/// dropped once there are more than the limit.
+pub struct History {
+    nodes: Vec<HistoryNode>,
+    current: usize,
+    dropped: usize,
+    merging: Option<ChangeKind>,
+    limit: usize,
+}
+
+impl History {
+    /// Create a history of an empty forest, keeping up to `limit` states.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            nodes: vec![HistoryNode {
+                forest: ForestState {
+                    focus: None,
+                    changed: false,
+                },
+                parent: None,
+                redo_child: None,
+                time: SystemTime::now(),
+                before_path: Vec::new(),
+                after_path: Vec::new(),
+            }],
+            current: 0,
+            dropped: 0,
+            merging: None,
+            limit: limit.max(1),
+        }
+    }
+
+    /// Restore a `saved` history, whose current state has the live `forest`,
+    /// keeping up to `limit` states.
+    pub fn restore(
+        saved: SavedHistory,
+        forest: &ForestState,
+        limit: usize,
+    ) -> Self {
+        let forests = saved.forests.into_forests();
+        let mut nodes: Vec<HistoryNode> = saved
+            .nodes
+            .into_iter()
+            .zip(forests)
+            .map(|(node, focus)| HistoryNode {
+                forest: ForestState {
+                    focus,
+                    changed: true,
+                },
+                parent: node.parent,
+                redo_child: node.redo_child,
+                time: node.time,
+                before_path: node.before_path,
+                after_path: node.after_path,
+            })
+            .collect();
+        nodes[saved.current].forest = forest.clone();
+        Self {
+            nodes,
+            current: saved.current,
+            dropped: 0,
+            merging: None,
+            limit: limit.max(1),
+        }
+    }
+
+    /// Return the current state and the states of the last `keep` changes
+    /// for saving, unless there are none.
+    pub fn save(&self, keep: usize) -> Option<SavedHistory> {
+        let len = self.nodes.len();
+        if keep == 0 || len == 1 {
+            return None;
+        }
+        let start = len.saturating_sub(keep + 1);
+        let kept: Vec<usize> = std::iter::once(self.current)
+            .filter(|&index| index < start)
+            .chain(start..len)
+            .collect();
+        let new_index = |index: usize| kept.binary_search(&index).ok();
+        let nodes = kept
+            .iter()
+            .map(|&index| {
+                let node = &self.nodes[index];
+                SavedNode {
+                    parent: node.parent.and_then(new_index),
+                    redo_child: node.redo_child.and_then(new_index),
+                    time: node.time,
+                    before_path: node.before_path.clone(),
+                    after_path: node.after_path.clone(),
+                }
+            })
+            .collect();
+        let forests = ForestPool::new(kept.iter().map(|&index| {
+            if index == self.current {
+                None
+            } else {
+                self.nodes[index].forest.focus.as_ref()
+            }
+        }));
+        Some(SavedHistory {
+            forests,
+            nodes,
+            current: new_index(self.current).expect("Current state is kept"),
+        })
+    }
+
+    /// Return the number of states created, which only grows with new
+    /// changes.
+    pub fn len(&self) -> usize {
+        self.dropped + self.nodes.len()
+    }
+
+    /// Return the number of the current state in creation order.
+    pub fn position(&self) -> usize {
+        self.dropped + self.current
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.nodes[self.current].parent.is_some()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.nodes[self.current].redo_child.is_some()
+    }
+
+    /// Record a change from the `before` forest to the `after` forest as a
+    /// new branch of the current state.
+    pub fn push(&mut self, before: ForestState, after: &ForestState) {
+        self.merging = None;
+        let index = self.nodes.len();
+        self.nodes[self.current].redo_child = Some(index);
+        self.nodes.push(HistoryNode {
+            forest: before.clone(),
+            parent: Some(self.current),
+            redo_child: None,
+            time: SystemTime::now(),
+            before_path: focus_path(&before),
+            after_path: focus_path(after),
+        });
+        self.nodes[self.current].forest = before;
+        self.current = index;
+        self.drop_oldest();
+    }
+
+    /// Record a change of the `kind` from the `before` forest to the `after`
+    /// forest, merging it into the latest change if that was of the same
+    /// kind.
+    pub fn merge(
+        &mut self,
+        kind: ChangeKind,
+        before: ForestState,
+        after: &ForestState,
+    ) {
+        let latest = self.current + 1 == self.nodes.len();
+        if latest && self.merging == Some(kind) {
+            self.nodes[self.current].after_path = focus_path(after);
+        } else {
+            self.push(before, after);
+            self.merging = Some(kind);
+        }
+    }
+
+    /// Record the next change as a new step, even if it is of the same kind
+    /// as the latest.
+    pub fn end_merge(&mut self) {
+        self.merging = None;
+    }
+
+    // Drop the oldest states beyond the limit, leaving the states
+    // that followed them without a parent.
+    fn drop_oldest(&mut self) {
+        let excess = self.nodes.len().saturating_sub(self.limit);
+        if excess == 0 {
+            return;
+        }
+        self.nodes.drain(..excess);
+        for node in &mut self.nodes {
+            node.parent =
+                node.parent.and_then(|index| index.checked_sub(excess));
+            node.redo_child =
+                node.redo_child.and_then(|index| index.checked_sub(excess));
+        }
+        self.current = self.current.saturating_sub(excess);
+        self.dropped += excess;
+    }
+
+    /// Return the current state and the number of states, to squash the
+    /// changes that follow.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            position: self.position(),
+            len: self.len(),
+        }
+    }
+
+    /// Replace the states created since the `checkpoint` with a single
+    /// change from the `before` forest to the `after` forest, unless some
+    /// have been dropped or they do not lead in a line from the state current
+    /// at the checkpoint to the current state.
+    pub fn squash(
+        &mut self,
+        checkpoint: Checkpoint,
+        before: ForestState,
+        after: &ForestState,
+    ) {
+        let (Some(start), Some(first)) = (
+            checkpoint.position.checked_sub(self.dropped),
+            checkpoint.len.checked_sub(self.dropped),
+        ) else {
+            return;
+        };
+        let len = self.nodes.len();
+        let linear = first < len
+            && self.current + 1 == len
+            && (first..len).all(|index| {
+                let parent = if index == first { start } else { index - 1 };
+                self.nodes[index].parent == Some(parent)
+            });
+        if !linear {
+            return;
+        }
+        self.nodes.truncate(first);
+        for node in &mut self.nodes {
+            if node.redo_child.is_some_and(|index| index >= first) {
+                node.redo_child = None;
+            }
+        }
+        self.current = start;
+        self.push(before, after);
+    }
+
+    // Leave the current state with the live `forest` for the `target` state,
+    // pointing redo along the path to it, and return its forest focused on
+    // the node of the change undone, or else of the change that led to it.
+    fn go_to(&mut self, target: usize, forest: ForestState) -> ForestState {
+        self.merging = None;
+        if target == self.current {
+            return forest;
+        }
+        let current = &self.nodes[self.current];
+        let path = match current.parent == Some(target) {
+            true => current.before_path.clone(),
+            false => self.nodes[target].after_path.clone(),
+        };
+        self.nodes[self.current].forest = forest;
+        let mut index = target;
+        while let Some(parent) = self.nodes[index].parent {
+            self.nodes[parent].redo_child = Some(index);
+            index = parent;
+        }
+        self.current = target;
+        let ForestState { focus, changed } = self.nodes[target].forest.clone();
+        ForestState {
+            focus: focus.map(|focus| focus.focus_path(&path)),
+            changed,
+        }
+    }
+
+    /// Return the forest before the change that led to the live `forest`.
+    pub fn undo(&mut self, forest: ForestState) -> ForestState {
+        match self.nodes[self.current].parent {
+            Some(parent) => self.go_to(parent, forest),
+            None => forest,
+        }
+    }
+
+    /// Return the forest after the most recently visited change from the
+    /// live `forest`.
+    pub fn redo(&mut self, forest: ForestState) -> ForestState {
+        match self.nodes[self.current].redo_child {
+            Some(child) => self.go_to(child, forest),
+            None => forest,
+        }
+    }
+
+    /// Return the forest `count` states earlier in creation order, across
+    /// branches.
+    pub fn older(&mut self, count: usize, forest: ForestState) -> ForestState {
+        self.go_to(self.current.saturating_sub(count), forest)
+    }
+
+    /// Return the forest `count` states later in creation order, across
+    /// branches.
+    pub fn newer(&mut self, count: usize, forest: ForestState) -> ForestState {
+        let target =
+            self.current.saturating_add(count).min(self.nodes.len() - 1);
+        self.go_to(target, forest)
+    }
+
+    /// Return the forest as it was the `duration` before the current state
+    /// was created.
+    pub fn earlier(
+        &mut self,
+        duration: Duration,
+        forest: ForestState,
+    ) -> ForestState {
+        let time = self.nodes[self.current].time;
+        let target = time.checked_sub(duration).map_or(0, |time| self.at(time));
+        self.go_to(target, forest)
+    }
+
+    /// Return the forest as it was the `duration` after the current state was
+    /// created.
+    pub fn later(
+        &mut self,
+        duration: Duration,
+        forest: ForestState,
+    ) -> ForestState {
+        let time = self.nodes[self.current].time;
+        let target = time
+            .checked_add(duration)
+            .map_or(self.nodes.len() - 1, |time| self.at(time));
+        self.go_to(target, forest)
+    }
+
+    // Return the index of the last state created at or before the `time`.
+    fn at(&self, time: SystemTime) -> usize {
+        self.nodes
+            .partition_point(|node| node.time <= time)
+            .saturating_sub(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{label, state};
+
+    const LIMIT: usize = 100;
+
+    // Record a change of the `live` forest to a forest labelled `after`.
+    fn edit(history: &mut History, live: &mut ForestState, after: &str) {
+        let before = std::mem::replace(live, state(after));
+        history.push(before, live);
+    }
+
+    // A history whose live forest went through the states labelled `labels`.
+    fn linear(labels: &[&str]) -> (History, ForestState) {
+        let mut history = History::new(LIMIT);
+        let mut live = state(labels[0]);
+        for after in &labels[1..] {
+            edit(&mut history, &mut live, after);
+        }
+        (history, live)
+    }
+
+    #[test]
+    fn undo_redo() {
+        let (mut history, live) = linear(&["0", "1", "2"]);
+        let live = history.undo(live);
+        assert_eq!(label(&live), "1");
+        let live = history.undo(live);
+        assert_eq!(label(&live), "0");
+        assert!(!history.can_undo());
+        let live = history.redo(live);
+        let live = history.redo(live);
+        assert_eq!(label(&live), "2");
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn merge_repeated_kind() {
+        let mut history = History::new(LIMIT);
+        let mut live = state("0");
+        for after in ["1", "2", "3"] {
+            let before = std::mem::replace(&mut live, state(after));
+            history.merge(ChangeKind::Promote, before, &live);
+        }
+        assert_eq!(history.len(), 2);
+        let before = std::mem::replace(&mut live, state("4"));
+        history.merge(ChangeKind::Demote, before, &live);
+        history.end_merge();
+        let before = std::mem::replace(&mut live, state("5"));
+        history.merge(ChangeKind::Demote, before, &live);
+        assert_eq!(history.len(), 4);
+        let live = history.undo(live);
+        assert_eq!(label(&live), "4");
+        let live = history.undo(live);
+        assert_eq!(label(&live), "3");
+        let live = history.undo(live);
+        assert_eq!(label(&live), "0");
+    }
+
+    #[test]
+    fn squash_after_drop_oldest() {
+        let mut history = History::new(3);
+        let mut live = state("0");
+        edit(&mut history, &mut live, "1");
+        let checkpoint = history.checkpoint();
+        let before = live.clone();
+        edit(&mut history, &mut live, "2");
+        edit(&mut history, &mut live, "3");
+        assert_eq!(history.dropped, 1);
+        history.squash(checkpoint, before, &live);
+        assert_eq!(history.len(), 3);
+        let live = history.undo(live);
+        assert_eq!(label(&live), "1");
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn squash_dropped_states() {
+        let mut history = History::new(2);
+        let mut live = state("0");
+        let checkpoint = history.checkpoint();
+        let before = live.clone();
+        edit(&mut history, &mut live, "1");
+        edit(&mut history, &mut live, "2");
+        history.squash(checkpoint, before, &live);
+        assert_eq!(history.len(), 3);
+        let live = history.undo(live);
+        assert_eq!(label(&live), "1");
+    }
+
+    #[test]
+    fn squash_linear_changes() {
+        let (mut history, live) = linear(&["0", "1", "2"]);
+        let live = history.undo(live);
+        let checkpoint = history.checkpoint();
+        let before = live.clone();
+        let mut live = live;
+        edit(&mut history, &mut live, "3");
+        edit(&mut history, &mut live, "4");
+        history.squash(checkpoint, before, &live);
+        assert_eq!(history.len(), 4);
+        let live = history.undo(live);
+        assert_eq!(label(&live), "1");
+        let live = history.redo(live);
+        assert_eq!(label(&live), "4");
+        let live = history.older(1, live);
+        assert_eq!(label(&live), "2");
+    }
+
+    #[test]
+    fn squash_after_undo_past_start() {
+        let (mut history, live) = linear(&["0", "1"]);
+        let checkpoint = history.checkpoint();
+        let before = live.clone();
+        let mut live = history.undo(live);
+        edit(&mut history, &mut live, "2");
+        history.squash(checkpoint, before, &live);
+        assert_eq!(history.len(), 3);
+        let live = history.undo(live);
+        assert_eq!(label(&live), "0");
+        let live = history.redo(live);
+        assert_eq!(label(&live), "2");
+        let live = history.older(1, live);
+        assert_eq!(label(&live), "1");
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn go_to_across_branches() {
+        let (mut history, live) = linear(&["0", "1"]);
+        let mut live = history.undo(live);
+        edit(&mut history, &mut live, "2");
+        let live = history.older(1, live);
+        assert_eq!(label(&live), "1");
+        let live = history.undo(live);
+        let live = history.redo(live);
+        assert_eq!(label(&live), "1");
+        let live = history.newer(5, live);
+        assert_eq!(label(&live), "2");
+        let live = history.undo(live);
+        assert_eq!(label(&live), "0");
+    }
+
+    #[test]
+    fn earlier_later() {
+        let (mut history, live) = linear(&["0", "1", "2", "3"]);
+        let start = SystemTime::now();
+        for (index, node) in history.nodes.iter_mut().enumerate() {
+            node.time = start + Duration::from_secs(10 * index as u64);
+        }
+        let live = history.earlier(Duration::from_secs(15), live);
+        assert_eq!(label(&live), "1");
+        let live = history.later(Duration::from_secs(10), live);
+        assert_eq!(label(&live), "2");
+        let live = history.earlier(Duration::from_secs(3600), live);
+        assert_eq!(label(&live), "0");
+        let live = history.later(Duration::from_secs(3600), live);
+        assert_eq!(label(&live), "3");
+    }
+
+    #[test]
+    fn save_restore() {
+        let (mut history, live) = linear(&["0", "1", "2"]);
+        let mut live = history.undo(live);
+        edit(&mut history, &mut live, "3");
+        let live = history.undo(live);
+        assert!(history.save(0).is_none());
+        let saved = history.save(1).unwrap();
+        assert_eq!(saved.current, 0);
+        let mut history = History::restore(saved, &live, LIMIT);
+        assert!(!history.can_undo());
+        let live = history.redo(live);
+        assert_eq!(label(&live), "3");
+        let live = history.undo(live);
+        assert_eq!(label(&live), "1");
+        let live = history.newer(1, live);
+        assert_eq!(label(&live), "2");
+        let live = history.undo(live);
+        assert_eq!(label(&live), "1");
+    }
+
+    #[test]
+    fn save_nothing_to_undo() {
+        let history = History::new(LIMIT);
+        assert!(history.save(10).is_none());
+    }
+}
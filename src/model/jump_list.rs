@@ -0,0 +1,68 @@
+const MAX_JUMPS: usize = 100;
+
+/// Nodes focused before and after jumps, stored as node identifiers so they
+/// survive structural edits.
+#[derive(Default)]
+pub struct JumpList {
+    back: Vec<u64>,
+    forward: Vec<u64>,
+}
+
+// Pop identifiers from `from` until one other than `current` is found by
+// `find`, pushing `current` onto `to` and returning the node's path.
+fn follow<F>(
+    from: &mut Vec<u64>,
+    to: &mut Vec<u64>,
+    current: u64,
+    find: F,
+) -> Option<Vec<usize>>
+where
+    F: Fn(u64) -> Option<Vec<usize>>,
+{
+    while let Some(id) = from.pop() {
+        if id == current {
+            continue;
+        }
+        if let Some(path) = find(id) {
+            to.push(current);
+            return Some(path);
+        }
+    }
+    None
+}
+
+impl JumpList {
+    /// Return whether there are no jumps to go back or forward to.
+    pub fn is_empty(&self) -> bool {
+        self.back.is_empty() && self.forward.is_empty()
+    }
+
+    /// Remember the node `id` before a jump, forgetting any later jumps.
+    pub fn push(&mut self, id: u64) {
+        self.forward.clear();
+        if self.back.last() != Some(&id) {
+            self.back.push(id);
+        }
+        if self.back.len() > MAX_JUMPS {
+            self.back.remove(0);
+        }
+    }
+
+    /// Return the path of the latest earlier node that `find` locates,
+    /// skipping deleted nodes and remembering the `current` node.
+    pub fn back<F>(&mut self, current: u64, find: F) -> Option<Vec<usize>>
+    where
+        F: Fn(u64) -> Option<Vec<usize>>,
+    {
+        follow(&mut self.back, &mut self.forward, current, find)
+    }
+
+    /// Return the path of the next later node that `find` locates, skipping
+    /// deleted nodes and remembering the `current` node.
+    pub fn forward<F>(&mut self, current: u64, find: F) -> Option<Vec<usize>>
+    where
+        F: Fn(u64) -> Option<Vec<usize>>,
+    {
+        follow(&mut self.forward, &mut self.back, current, find)
+    }
+}
@@ -0,0 +1,153 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A single line of user input with a cursor at a grapheme boundary.
+#[derive(Default)]
+pub struct TextInput {
+    text: String,
+    cursor: usize,
+}
+
+impl From<String> for TextInput {
+    fn from(text: String) -> Self {
+        Self {
+            cursor: text.len(),
+            text,
+        }
+    }
+}
+
+impl TextInput {
+    /// Return the input text.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Return whether the input text is empty.
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Return whether the cursor is at the start of the text.
+    pub fn at_start(&self) -> bool {
+        self.cursor == 0
+    }
+
+    /// Return the text before the cursor.
+    pub fn before_cursor(&self) -> &str {
+        &self.text[..self.cursor]
+    }
+
+    /// Return the text before the cursor, the grapheme under the cursor (if
+    /// any) and the text after it.
+    pub fn split_at_cursor(&self) -> (&str, Option<&str>, &str) {
+        let (before, rest) = self.text.split_at(self.cursor);
+        match rest.graphemes(true).next() {
+            Some(grapheme) => (before, Some(grapheme), &rest[grapheme.len()..]),
+            None => (before, None, rest),
+        }
+    }
+
+    /// Insert a string at the cursor, moving the cursor after it.
+    pub fn insert_str(&mut self, s: &str) {
+        self.text.insert_str(self.cursor, s);
+        self.cursor += s.len();
+    }
+
+    /// Replace the text from the byte index `start` up to the cursor with
+    /// `s`, moving the cursor after it.
+    pub fn replace_before_cursor(&mut self, start: usize, s: &str) {
+        self.text.replace_range(start..self.cursor, s);
+        self.cursor = start + s.len();
+    }
+
+    /// Insert a character at the cursor, moving the cursor after it.
+    pub fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    // Byte index of the grapheme boundary before the cursor.
+    fn prev_boundary(&self) -> usize {
+        self.text[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(index, _)| index)
+    }
+
+    // Byte index of the grapheme boundary after the cursor.
+    fn next_boundary(&self) -> usize {
+        self.text[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map_or(self.cursor, |grapheme| self.cursor + grapheme.len())
+    }
+
+    // Byte index of the start of the word before the cursor.
+    fn prev_word_start(&self) -> usize {
+        let before = self.text[..self.cursor].trim_end();
+        before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(index, c)| index + c.len_utf8())
+    }
+
+    // Byte index of the end of the word after the cursor.
+    fn next_word_end(&self) -> usize {
+        let after = &self.text[self.cursor..];
+        let word = after.trim_start();
+        let start = self.cursor + after.len() - word.len();
+        word.find(char::is_whitespace)
+            .map_or(self.text.len(), |index| start + index)
+    }
+
+    /// Delete the grapheme before the cursor.
+    pub fn backspace(&mut self) {
+        let start = self.prev_boundary();
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    /// Delete the grapheme under the cursor.
+    pub fn delete(&mut self) {
+        let end = self.next_boundary();
+        self.text.replace_range(self.cursor..end, "");
+    }
+
+    /// Delete the word before the cursor.
+    pub fn delete_word(&mut self) {
+        let start = self.prev_word_start();
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    /// Move the cursor to the previous grapheme.
+    pub fn left(&mut self) {
+        self.cursor = self.prev_boundary();
+    }
+
+    /// Move the cursor to the next grapheme.
+    pub fn right(&mut self) {
+        self.cursor = self.next_boundary();
+    }
+
+    /// Move the cursor to the start of the previous word.
+    pub fn word_left(&mut self) {
+        self.cursor = self.prev_word_start();
+    }
+
+    /// Move the cursor to the end of the next word.
+    pub fn word_right(&mut self) {
+        self.cursor = self.next_word_end();
+    }
+
+    /// Move the cursor to the start of the text.
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    /// Move the cursor to the end of the text.
+    pub fn end(&mut self) {
+        self.cursor = self.text.len();
+    }
+}
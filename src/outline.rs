@@ -0,0 +1,184 @@
+use crate::zipper::Tree;
+
+// Columns occupied by a tab when measuring indentation.
+const TAB_WIDTH: usize = 4;
+
+/// Text format for exporting a forest.
+#[derive(Clone, Copy)]
+pub enum ExportFormat {
+    Markdown,
+    Indented,
+}
+
+// Return the indentation width and trimmed text of a line.
+fn split_indent(line: &str) -> (usize, &str) {
+    let text = line.trim_start();
+    let indent = line[..line.len() - text.len()]
+        .chars()
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum();
+    (indent, text.trim_end())
+}
+
+// Attach the top open tree to its parent, or to the roots if it has none.
+fn close_top(stack: &mut Vec<(usize, Tree)>, roots: &mut Vec<Tree>) {
+    if let Some((_, tree)) = stack.pop() {
+        match stack.last_mut() {
+            Some((_, parent)) => parent.children.push(tree),
+            None => roots.push(tree),
+        }
+    }
+}
+
+/// Parse indented text into trees, nesting each line under the nearest
+/// preceding line with less indentation.
+pub fn parse_indented(text: &str) -> Vec<Tree> {
+    let mut roots = Vec::new();
+    let mut stack: Vec<(usize, Tree)> = Vec::new();
+    for (indent, label) in text.lines().map(split_indent) {
+        if label.is_empty() {
+            continue;
+        }
+        while stack.last().is_some_and(|(open, _)| *open >= indent) {
+            close_top(&mut stack, &mut roots);
+        }
+        let tree = Tree {
+            label: label.to_string(),
+            children: Vec::new(),
+        };
+        stack.push((indent, tree));
+    }
+    while !stack.is_empty() {
+        close_top(&mut stack, &mut roots);
+    }
+    roots
+}
+
+/// Parse text into one tree per non-empty line, ignoring indentation.
+pub fn parse_lines(text: &str) -> Vec<Tree> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| Tree {
+            label: line.to_string(),
+            children: Vec::new(),
+        })
+        .collect()
+}
+
+// Insert the path `components` into the `trees`, reusing matching nodes.
+fn insert_path<'a>(
+    trees: &mut Vec<Tree>,
+    mut components: impl Iterator<Item = &'a str>,
+) {
+    let Some(label) = components.next() else {
+        return;
+    };
+    // Input is usually grouped by prefix, so search from the end.
+    let index = match trees.iter().rposition(|tree| tree.label == label) {
+        Some(index) => index,
+        None => {
+            trees.push(Tree {
+                label: label.to_string(),
+                children: Vec::new(),
+            });
+            trees.len() - 1
+        }
+    };
+    insert_path(&mut trees[index].children, components);
+}
+
+/// Parse one path per line into trees, splitting each path on `sep`.
+pub fn parse_paths(text: &str, sep: &str) -> Vec<Tree> {
+    let mut roots = Vec::new();
+    for line in text.lines() {
+        let components = line
+            .split(sep)
+            .map(str::trim)
+            .filter(|component| !component.is_empty());
+        insert_path(&mut roots, components);
+    }
+    roots
+}
+
+// Append each tree's label to `out` on its own line below `depth` levels of
+// indentation, preceded by the `marker`.
+fn write_trees(trees: &[Tree], depth: usize, marker: &str, out: &mut String) {
+    for tree in trees {
+        out.push_str(&"  ".repeat(depth));
+        out.push_str(marker);
+        out.push_str(&tree.label);
+        out.push('\n');
+        write_trees(&tree.children, depth + 1, marker, out);
+    }
+}
+
+/// Format the trees as text in the given `format`.
+pub fn export(trees: &[Tree], format: ExportFormat) -> String {
+    let marker = match format {
+        ExportFormat::Markdown => "- ",
+        ExportFormat::Indented => "",
+    };
+    let mut out = String::new();
+    write_trees(trees, 0, marker, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Format the trees as indented text, two spaces per level.
+    fn indented(trees: &[Tree]) -> String {
+        export(trees, ExportFormat::Indented)
+    }
+
+    #[test]
+    fn indented_nesting() {
+        let trees = parse_indented("a\n  a1\n  a2\n    a2x\nb\n  b1\n");
+        assert_eq!(indented(&trees), "a\n  a1\n  a2\n    a2x\nb\n  b1\n");
+    }
+
+    #[test]
+    fn indented_uneven() {
+        // A line between two levels nests under the nearest shallower line.
+        let trees = parse_indented("a\n    b\n  c\n   d\n e\n");
+        assert_eq!(indented(&trees), "a\n  b\n  c\n    d\n  e\n");
+        let trees = parse_indented("   a\n b\nc");
+        assert_eq!(indented(&trees), "a\nb\nc\n");
+    }
+
+    #[test]
+    fn indented_tabs_and_blanks() {
+        let trees = parse_indented("a\n\tb  \n\n    c\n   \n\t\td\n");
+        assert_eq!(indented(&trees), "a\n  b\n  c\n    d\n");
+        assert!(parse_indented("").is_empty());
+        assert!(parse_indented(" \n\t\n").is_empty());
+    }
+
+    #[test]
+    fn indented_round_trip() {
+        let text = "a\n  a1\n    a1x\n  a2\nb\n";
+        assert_eq!(indented(&parse_indented(text)), text);
+    }
+
+    #[test]
+    fn paths_merge_prefixes() {
+        let trees = parse_paths("a/b/c\na/b/d\na/e\nf\n", "/");
+        assert_eq!(indented(&trees), "a\n  b\n    c\n    d\n  e\nf\n");
+    }
+
+    #[test]
+    fn paths_empty_components() {
+        let trees = parse_paths("/a//b/\n.\n\n/\n", "/");
+        assert_eq!(indented(&trees), "a\n  b\n.\n");
+        let trees = parse_paths("a :: b\na::c", "::");
+        assert_eq!(indented(&trees), "a\n  b\n  c\n");
+    }
+
+    #[test]
+    fn paths_ungrouped() {
+        let trees = parse_paths("a/x\nb\na/y\na/x/z\n", "/");
+        assert_eq!(indented(&trees), "a\n  x\n    z\n  y\nb\n");
+    }
+}
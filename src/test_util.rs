@@ -0,0 +1,38 @@
+use crate::{
+    model::ForestState,
+    zipper::{FocusNode, Tree},
+};
+
+/// Build a tree with the `label` and `children`.
+pub fn tree(label: &str, children: Vec<Tree>) -> Tree {
+    Tree {
+        label: label.to_string(),
+        children,
+    }
+}
+
+/// The forest a(a1, a2(a2x)), b(b1), c, focused on the node at `path`.
+pub fn sample_forest(path: &[usize]) -> FocusNode {
+    let trees = vec![
+        tree(
+            "a",
+            vec![tree("a1", vec![]), tree("a2", vec![tree("a2x", vec![])])],
+        ),
+        tree("b", vec![tree("b1", vec![])]),
+        tree("c", vec![]),
+    ];
+    FocusNode::from_trees(trees).unwrap().focus_path(path)
+}
+
+/// A changed forest of a single node with the `label`.
+pub fn state(label: &str) -> ForestState {
+    ForestState {
+        focus: FocusNode::from_trees(vec![tree(label, vec![])]),
+        changed: true,
+    }
+}
+
+/// Return the label of the focused node in the `forest`.
+pub fn label(forest: &ForestState) -> String {
+    forest.focus.as_ref().unwrap().clone_label()
+}
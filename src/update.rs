@@ -1,16 +1,31 @@
+mod command_line;
+mod replay;
+
 use crate::{
     io::Command,
     message::{
-        ConfirmMsg, FilenameMsg, InputEdit, InsertMsg, LabelMsg, LoadMsg,
-        Message, MoveMsg, NormalMsg, SaveMsg,
+        CommandLineMsg, ConfirmMsg, FilenameMsg, HelpMsg, InputEdit, InsertMsg,
+        LabelMsg, LoadMsg, MarkMsg, Message, MoveMsg, NormalMsg, PasteMsg,
+        SaveMsg, SearchMsg, VisualMsg,
     },
     model::{
-        ConfirmState, FilenameAction, FilenameState, FilenameStatus,
-        LabelState, LoadState, Model, SaveState, SessionState,
+        CommandLineState, ConfirmState, EditStep, FilenameAction,
+        FilenameState, FilenameStatus, LabelState, LoadState, MarkAction,
+        MarkState, Model, PasteState, SaveState, SearchState, SessionState,
+        VisualState, help::HelpState, history::ChangeKind,
+        text_input::TextInput,
     },
     zipper::FocusNode,
 };
 
+// Return a function applying `f` to the focused node `count` times.
+fn repeat<F>(count: usize, f: F) -> impl FnOnce(FocusNode) -> FocusNode
+where
+    F: Fn(FocusNode) -> FocusNode,
+{
+    move |focus| (0..count).fold(focus, |focus, _| f(focus))
+}
+
 // Update the Model based on a Load mode message.
 fn update_load(msg: LoadMsg, load_state: LoadState) -> Command {
     let model = match msg {
@@ -32,17 +47,66 @@ fn update_load(msg: LoadMsg, load_state: LoadState) -> Command {
 
 // Update the Model based on a Normal mode message.
 fn update_normal(msg: NormalMsg, state: SessionState) -> Command {
+    let mut state = state.end_merge();
+    let count = state.take_count();
+    if matches!(
+        msg,
+        NormalMsg::Edit
+            | NormalMsg::Insert
+            | NormalMsg::Move
+            | NormalMsg::Nest
+            | NormalMsg::Flatten
+            | NormalMsg::Delete
+            | NormalMsg::Put
+    ) {
+        state = state.begin_edit(EditStep::Normal(msg));
+    }
     let model = match msg {
-        NormalMsg::Ascend => {
-            Model::Normal(state.navigate(FocusNode::focus_parent))
-        }
+        NormalMsg::Ascend => Model::Normal(
+            state.navigate(repeat(count, FocusNode::focus_parent)),
+        ),
         NormalMsg::Descend => {
-            Model::Normal(state.navigate(FocusNode::focus_child))
+            Model::Normal(state.navigate(repeat(count, FocusNode::focus_child)))
         }
         NormalMsg::Previous => {
-            Model::Normal(state.navigate(FocusNode::focus_prev))
+            Model::Normal(state.navigate(repeat(count, FocusNode::focus_prev)))
+        }
+        NormalMsg::Next => {
+            Model::Normal(state.navigate(repeat(count, FocusNode::focus_next)))
+        }
+        NormalMsg::FirstSibling => {
+            Model::Normal(state.navigate(FocusNode::focus_first_sibling))
+        }
+        NormalMsg::LastSibling => {
+            Model::Normal(state.navigate(FocusNode::focus_last_sibling))
+        }
+        NormalMsg::Top => {
+            Model::Normal(state.jump(FocusNode::focus_first_root))
+        }
+        NormalMsg::Bottom => {
+            Model::Normal(state.jump(FocusNode::focus_last_visible))
+        }
+        NormalMsg::PrevVisible => Model::Normal(
+            state.navigate(repeat(count, FocusNode::focus_prev_visible)),
+        ),
+        NormalMsg::NextVisible => Model::Normal(
+            state.navigate(repeat(count, FocusNode::focus_next_visible)),
+        ),
+        NormalMsg::ParentNext => Model::Normal(
+            state.navigate(repeat(count, FocusNode::focus_parent_next)),
+        ),
+        NormalMsg::PageUp => {
+            let rows = count * state.page_height.saturating_sub(1).max(1);
+            Model::Normal(
+                state.navigate(repeat(rows, FocusNode::focus_prev_visible)),
+            )
+        }
+        NormalMsg::PageDown => {
+            let rows = count * state.page_height.saturating_sub(1).max(1);
+            Model::Normal(
+                state.navigate(repeat(rows, FocusNode::focus_next_visible)),
+            )
         }
-        NormalMsg::Next => Model::Normal(state.navigate(FocusNode::focus_next)),
         NormalMsg::Edit => match state.clone_label() {
             Some(label) => {
                 Model::LabelInput(LabelState::new_rename(label, state))
@@ -63,21 +127,65 @@ fn update_normal(msg: NormalMsg, state: SessionState) -> Command {
                 Model::Move(state)
             }
         }
-        NormalMsg::Nest => Model::Normal(state.map_focus(FocusNode::nest)),
+        NormalMsg::Visual => {
+            if state.is_empty() {
+                Model::Normal(state)
+            } else {
+                Model::Visual(VisualState::new(state, 1))
+            }
+        }
+        NormalMsg::Nest => {
+            Model::Normal(state.map_focus(FocusNode::nest).finish_edit())
+        }
         NormalMsg::Flatten => {
-            Model::Normal(state.map_focus(FocusNode::flatten))
+            Model::Normal(state.map_focus(FocusNode::flatten).finish_edit())
         }
         NormalMsg::Delete => {
             if state.is_empty() {
                 Model::Normal(state)
             } else {
-                Model::Confirm(ConfirmState::DeleteItem(state))
+                Model::Confirm(ConfirmState::DeleteItem(Box::new(state)))
             }
         }
-        NormalMsg::Undo => Model::Normal(state.undo()),
-        NormalMsg::Redo => Model::Normal(state.redo()),
+        NormalMsg::Put => Model::Normal(state.put().finish_edit()),
+        NormalMsg::Undo => Model::Normal(state.undo_n(count)),
+        NormalMsg::Redo => Model::Normal(state.redo_n(count)),
+        NormalMsg::Older => Model::Normal(state.older(count)),
+        NormalMsg::Newer => Model::Normal(state.newer(count)),
+        NormalMsg::Fold => {
+            Model::Normal(state.navigate(FocusNode::toggle_fold))
+        }
+        NormalMsg::Hoist => Model::Normal(state.hoist()),
+        NormalMsg::Unhoist => Model::Normal(state.unhoist()),
+        NormalMsg::UnhoistAll => Model::Normal(state.unhoist_all()),
+        NormalMsg::Search => Model::Search(SearchState::new(state)),
+        NormalMsg::NextMatch => Model::Normal(
+            (0..count).fold(state, |state, _| state.find_match(true)),
+        ),
+        NormalMsg::PrevMatch => Model::Normal(
+            (0..count).fold(state, |state, _| state.find_match(false)),
+        ),
+        NormalMsg::SetMark | NormalMsg::JumpMark if state.is_empty() => {
+            Model::Normal(state)
+        }
+        NormalMsg::SetMark => Model::Mark(MarkState {
+            action: MarkAction::Set,
+            session: state,
+        }),
+        NormalMsg::JumpMark => Model::Mark(MarkState {
+            action: MarkAction::Jump,
+            session: state,
+        }),
+        NormalMsg::JumpBack => Model::Normal(state.jump_back(count)),
+        NormalMsg::JumpForward => Model::Normal(state.jump_forward(count)),
+        NormalMsg::CommandLine => {
+            Model::CommandLine(CommandLineState::new(state))
+        }
+        NormalMsg::Repeat => Model::Normal(replay::replay(count, state)),
         NormalMsg::Quit => {
-            if state.is_changed() {
+            if state.read_only {
+                return Command::Quit;
+            } else if state.is_changed() {
                 Model::Save(SaveState::new(state))
             } else {
                 return Command::Load { quit: true };
@@ -90,6 +198,7 @@ fn update_normal(msg: NormalMsg, state: SessionState) -> Command {
 // Update the Model based on an Insert mode message.
 fn update_insert(msg: InsertMsg, state: SessionState) -> Model {
     let fallback = state.forest.clone();
+    let state = state.record_edit(EditStep::Insert(msg));
     let state = match msg {
         InsertMsg::Parent => state.insert(FocusNode::insert_parent),
         InsertMsg::Child => state.insert(FocusNode::insert_child),
@@ -101,17 +210,104 @@ fn update_insert(msg: InsertMsg, state: SessionState) -> Model {
 }
 
 // Update the Model based on a Move mode message.
-fn update_move(msg: MoveMsg, state: SessionState) -> Model {
+fn update_move(msg: MoveMsg, mut state: SessionState) -> Model {
+    let count = state.take_count();
+    if msg == MoveMsg::Done {
+        let state = state.record_edit(EditStep::Move(msg));
+        return Model::Normal(state.finish_edit());
+    }
+    let state = (0..count)
+        .fold(state, |state, _| state.record_edit(EditStep::Move(msg)));
     let state = match msg {
-        MoveMsg::Promote => state.map_focus(FocusNode::promote),
-        MoveMsg::Demote => state.map_focus(FocusNode::demote),
-        MoveMsg::Backward => state.map_focus(FocusNode::swap_prev),
-        MoveMsg::Forward => state.map_focus(FocusNode::swap_next),
+        MoveMsg::Promote => state.merge_focus(
+            ChangeKind::Promote,
+            repeat(count, FocusNode::promote),
+        ),
+        MoveMsg::Demote => state
+            .merge_focus(ChangeKind::Demote, repeat(count, FocusNode::demote)),
+        MoveMsg::Backward => state.merge_focus(
+            ChangeKind::Backward,
+            repeat(count, FocusNode::swap_prev),
+        ),
+        MoveMsg::Forward => state.merge_focus(
+            ChangeKind::Forward,
+            repeat(count, FocusNode::swap_next),
+        ),
         MoveMsg::Done => return Model::Normal(state),
     };
     Model::Move(state)
 }
 
+// Update the Model based on a Visual mode message.
+fn update_visual(msg: VisualMsg, visual_state: VisualState) -> Model {
+    let range = visual_state.range();
+    let VisualState {
+        anchor,
+        session: mut state,
+    } = visual_state;
+    let count = state.take_count();
+    let len = range.len();
+    let state = match msg {
+        VisualMsg::Previous | VisualMsg::Next => {
+            let state = if msg == VisualMsg::Previous {
+                state.navigate(repeat(count, FocusNode::focus_prev))
+            } else {
+                state.navigate(repeat(count, FocusNode::focus_next))
+            };
+            return Model::Visual(VisualState {
+                anchor,
+                session: state.end_merge(),
+            });
+        }
+        VisualMsg::Yank => return Model::Normal(state.yank_range(&range)),
+        VisualMsg::Cancel => return Model::Normal(state),
+        _ => state.begin_edit(EditStep::Visual(msg, len)),
+    };
+    let state = match msg {
+        VisualMsg::Backward => state.map_range(
+            &range,
+            Some(ChangeKind::Backward),
+            repeat(count, FocusNode::swap_prev),
+        ),
+        VisualMsg::Forward => state.map_range(
+            &range,
+            Some(ChangeKind::Forward),
+            repeat(count, FocusNode::swap_next),
+        ),
+        VisualMsg::Promote => state.map_range(
+            &range,
+            Some(ChangeKind::Promote),
+            repeat(count, FocusNode::promote),
+        ),
+        VisualMsg::Demote => state.map_range(
+            &range,
+            Some(ChangeKind::Demote),
+            repeat(count, FocusNode::demote),
+        ),
+        VisualMsg::Sort => {
+            let state = state.map_range(&range, None, |focus| {
+                focus.focus_child().sort_siblings().focus_parent()
+            });
+            return Model::Normal(state.finish_edit());
+        }
+        VisualMsg::Delete => {
+            return Model::Normal(state.delete_range(&range).finish_edit());
+        }
+        VisualMsg::Wrap => {
+            let fallback = state.forest.clone();
+            let state = state
+                .navigate(|focus| focus.focus_path(&range.start_path()))
+                .insert(|focus| focus.wrap_range(len));
+            return Model::LabelInput(LabelState::new_insert(state, fallback));
+        }
+        VisualMsg::Previous
+        | VisualMsg::Next
+        | VisualMsg::Yank
+        | VisualMsg::Cancel => unreachable!("Handled without an edit"),
+    };
+    Model::Visual(VisualState::new(state.finish_edit(), len))
+}
+
 // Update the Model based on a Save mode message.
 fn update_save(msg: SaveMsg, save_state: SaveState) -> Command {
     let model = match msg {
@@ -134,21 +330,68 @@ fn update_save(msg: SaveMsg, save_state: SaveState) -> Command {
     Command::None(model)
 }
 
+// Update the Model based on a Paste mode message.
+fn update_paste(msg: PasteMsg, paste_state: PasteState) -> Model {
+    match msg {
+        PasteMsg::Toggle => Model::Paste(paste_state.toggle()),
+        PasteMsg::Confirm => {
+            let step = EditStep::Paste(
+                paste_state.text.clone(),
+                paste_state.as_outline,
+            );
+            let (trees, label_state) = paste_state.into_trees();
+            if trees.is_empty() {
+                Model::LabelInput(label_state)
+            } else {
+                let state = label_state.insert_trees(trees);
+                Model::Normal(state.record_edit(step).finish_edit())
+            }
+        }
+        PasteMsg::Cancel => Model::LabelInput(paste_state.label_state),
+    }
+}
+
+// Apply an `edit` to the text `input`, ignoring spaces typed at the start if
+// `trim_start` is set.
+fn edit_text(input: &mut TextInput, edit: InputEdit, trim_start: bool) {
+    match edit {
+        InputEdit::Append(' ') if trim_start && input.at_start() => (),
+        InputEdit::Paste(text) if trim_start && input.at_start() => {
+            input.insert_str(text.trim_start())
+        }
+        InputEdit::Paste(text) => input.insert_str(&text),
+        InputEdit::Append(c) => input.insert(c),
+        InputEdit::PopChar => input.backspace(),
+        InputEdit::Delete => input.delete(),
+        InputEdit::DeleteWord => input.delete_word(),
+        InputEdit::Left => input.left(),
+        InputEdit::Right => input.right(),
+        InputEdit::WordLeft => input.word_left(),
+        InputEdit::WordRight => input.word_right(),
+        InputEdit::Home => input.home(),
+        InputEdit::End => input.end(),
+    }
+}
+
 // Update the Model based on a Label Input mode message.
 fn update_label_input(msg: LabelMsg, label_state: LabelState) -> Model {
     match msg {
         LabelMsg::Edit(edit) => {
-            let label_state = match edit {
-                InputEdit::Append(c) => label_state.append(c),
-                InputEdit::PopChar => label_state.pop(),
-            };
+            let mut label_state = label_state;
+            edit_text(&mut label_state.input, edit, true);
             Model::LabelInput(label_state)
         }
+        LabelMsg::PasteLines(text) => {
+            Model::Paste(PasteState::new(text, label_state))
+        }
         LabelMsg::Submit => {
             if label_state.input.is_empty() {
                 Model::LabelInput(label_state)
             } else {
-                Model::Normal(label_state.set_label())
+                let label = label_state.input.as_str().trim().to_string();
+                let state = label_state.set_label();
+                let state = state.record_edit(EditStep::Label(label));
+                Model::Normal(state.finish_edit())
             }
         }
         LabelMsg::Cancel => Model::Normal(label_state.fallback()),
@@ -162,10 +405,8 @@ fn update_filename_input(
 ) -> Command {
     let filename_state = match msg {
         FilenameMsg::Edit(edit) => {
-            let filename_state = match edit {
-                InputEdit::Append(c) => filename_state.append(c),
-                InputEdit::PopChar => filename_state.pop(),
-            };
+            let mut filename_state = filename_state;
+            edit_text(&mut filename_state.input, edit, true);
             if filename_state.input.is_empty() {
                 filename_state.set_status(FilenameStatus::Empty)
             } else {
@@ -182,10 +423,10 @@ fn update_filename_input(
                         Command::RenameFile(filename, load_state)
                     }
                     FilenameAction::SaveNew(session) => {
-                        let focus = session.forest.focus.clone();
+                        let focus = session.whole_forest().focus;
                         let initial_focus =
                             focus.map(FocusNode::focus_first_root);
-                        Command::SaveNew(initial_focus, filename, session)
+                        Command::SaveNew(initial_focus, filename, *session)
                     }
                 };
             }
@@ -193,7 +434,7 @@ fn update_filename_input(
         FilenameMsg::Cancel => {
             let model = match filename_state.action {
                 FilenameAction::Rename(load_state) => Model::Load(load_state),
-                FilenameAction::SaveNew(session) => Model::Normal(session),
+                FilenameAction::SaveNew(session) => Model::Normal(*session),
             };
             return Command::None(model);
         }
@@ -201,12 +442,70 @@ fn update_filename_input(
     Command::None(Model::FilenameInput(filename_state))
 }
 
+// Update the Model based on a Search mode message.
+fn update_search(msg: SearchMsg, search_state: SearchState) -> Model {
+    match msg {
+        SearchMsg::Edit(edit) => {
+            let mut search_state = search_state;
+            edit_text(&mut search_state.input, edit, false);
+            Model::Search(search_state)
+        }
+        SearchMsg::Submit => {
+            if search_state.input.is_empty() {
+                Model::Search(search_state)
+            } else {
+                Model::Normal(search_state.submit())
+            }
+        }
+        SearchMsg::Cancel => Model::Normal(search_state.session),
+    }
+}
+
+// Update the Model based on a Command Line mode message.
+fn update_command_line(
+    msg: CommandLineMsg,
+    command_line_state: CommandLineState,
+) -> Command {
+    let command_line_state = match msg {
+        CommandLineMsg::Edit(edit) => {
+            let mut command_line_state = command_line_state;
+            edit_text(&mut command_line_state.input, edit, false);
+            command_line_state.completions.clear();
+            command_line_state
+        }
+        CommandLineMsg::Complete => {
+            let candidates = command_line::completions(
+                command_line_state.input.before_cursor(),
+            );
+            command_line_state.complete(candidates)
+        }
+        CommandLineMsg::Older => command_line_state.older(),
+        CommandLineMsg::Newer => command_line_state.newer(),
+        CommandLineMsg::Submit => {
+            if command_line_state.input.as_str().trim().is_empty() {
+                return Command::None(Model::Normal(
+                    command_line_state.session,
+                ));
+            }
+            let (input, session) = command_line_state.submit();
+            return command_line::run(&input, session);
+        }
+        CommandLineMsg::Cancel => {
+            return Command::None(Model::Normal(command_line_state.session));
+        }
+    };
+    Command::None(Model::CommandLine(command_line_state))
+}
+
 // Update the Model based on a Confirm mode message.
 fn update_confirm(msg: ConfirmMsg, confirm_state: ConfirmState) -> Command {
     let model = match msg {
         ConfirmMsg::Confirm => match confirm_state {
             ConfirmState::NewSession => Model::Normal(SessionState::new()),
-            ConfirmState::DeleteItem(state) => Model::Normal(state.delete()),
+            ConfirmState::DeleteItem(state) => {
+                let state = state.delete().record_edit(EditStep::Confirm);
+                Model::Normal(state.finish_edit())
+            }
             ConfirmState::DeleteFile(load_state) => {
                 return Command::DeleteFile(load_state);
             }
@@ -215,13 +514,36 @@ fn update_confirm(msg: ConfirmMsg, confirm_state: ConfirmState) -> Command {
             ConfirmState::NewSession => {
                 Model::Confirm(ConfirmState::NewSession)
             }
-            ConfirmState::DeleteItem(state) => Model::Normal(state),
+            ConfirmState::DeleteItem(state) => Model::Normal(*state),
             ConfirmState::DeleteFile(load_state) => Model::Load(load_state),
         },
     };
     Command::None(model)
 }
 
+// Update the Model based on a Mark mode message.
+fn update_mark(msg: MarkMsg, mark_state: MarkState) -> Model {
+    let MarkState { action, session } = mark_state;
+    let session = match (msg, action) {
+        (MarkMsg::Select(mark), MarkAction::Set) => session.set_mark(mark),
+        (MarkMsg::Select(mark), MarkAction::Jump) => session.jump_to_mark(mark),
+        (MarkMsg::Cancel, _) => session,
+    };
+    Model::Normal(session)
+}
+
+// Update the Model based on a help overlay message.
+fn update_help(msg: HelpMsg, help_state: HelpState) -> Model {
+    let page = help_state.page_height.saturating_sub(1).max(1);
+    match msg {
+        HelpMsg::Toggle | HelpMsg::Close => help_state.close(),
+        HelpMsg::Up => Model::Help(help_state.scroll_up(1)),
+        HelpMsg::Down => Model::Help(help_state.scroll_down(1)),
+        HelpMsg::PageUp => Model::Help(help_state.scroll_up(page)),
+        HelpMsg::PageDown => Model::Help(help_state.scroll_down(page)),
+    }
+}
+
 /// Update the Model based on the `message` and return an IO Command.
 pub fn update(message: Message) -> Command {
     let model = match message {
@@ -237,18 +559,42 @@ pub fn update(message: Message) -> Command {
         Message::Move(move_msg, session_state) => {
             update_move(move_msg, session_state)
         }
+        Message::Visual(visual_msg, visual_state) => {
+            update_visual(visual_msg, visual_state)
+        }
         Message::Save(save_msg, save_state) => {
             return update_save(save_msg, save_state);
         }
         Message::LabelInput(label_msg, label_state) => {
             update_label_input(label_msg, label_state)
         }
+        Message::Paste(paste_msg, paste_state) => {
+            update_paste(paste_msg, paste_state)
+        }
         Message::FilenameInput(filename_msg, filename_state) => {
             return update_filename_input(filename_msg, filename_state);
         }
+        Message::Search(search_msg, search_state) => {
+            update_search(search_msg, search_state)
+        }
+        Message::CommandLine(command_line_msg, command_line_state) => {
+            return update_command_line(command_line_msg, command_line_state);
+        }
+        Message::Mark(mark_msg, mark_state) => {
+            update_mark(mark_msg, mark_state)
+        }
+        Message::Help(help_msg, help_state) => {
+            update_help(help_msg, help_state)
+        }
         Message::Confirm(confirm_msg, confirm_state) => {
             return update_confirm(confirm_msg, confirm_state);
         }
+        Message::FocusNode(path, session_state) => Model::Normal(
+            session_state.navigate(|focus| focus.focus_path(&path)),
+        ),
+        Message::SelectFile(index, load_state) => {
+            Model::Load(load_state.select(index))
+        }
         Message::Continue(model) => model,
     };
     Command::None(model)
@@ -0,0 +1,270 @@
+use std::time::Duration;
+
+use crate::{
+    io::Command,
+    message::{NormalMsg, keymap::Action},
+    model::{Model, SessionState},
+    outline::ExportFormat,
+    update::update_normal,
+    zipper::FocusNode,
+};
+
+// Commands taking arguments, in addition to the Normal mode actions.
+const COMMANDS: [&str; 8] = [
+    "earlier", "export", "export!", "fold", "goto", "later", "saveas", "sort",
+];
+const EXPORT_FORMATS: [&str; 2] = ["md", "txt"];
+const FOLD_TARGETS: [&str; 2] = ["all", "none"];
+
+// A command parsed from the command line.
+enum NamedCommand {
+    Action(NormalMsg),
+    Sort,
+    Export(ExportFormat, String, bool),
+    SaveAs(String),
+    Goto(Vec<usize>),
+    FoldAll(bool),
+    Earlier(Travel),
+    Later(Travel),
+}
+
+// Distance to travel through the undo history.
+enum Travel {
+    Steps(usize),
+    Time(Duration),
+}
+
+// Parse a dot-separated path of one-based sibling indices.
+fn parse_path(text: &str) -> Option<Vec<usize>> {
+    text.split('.')
+        .map(|index| index.parse::<usize>().ok()?.checked_sub(1))
+        .collect()
+}
+
+// Parse a number of history steps, or a time such as `10s`, `5m`, `2h` or
+// `1d`.
+fn parse_travel(text: &str) -> Option<Travel> {
+    let unit = match text.chars().last()? {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        _ => return text.parse().ok().map(Travel::Steps),
+    };
+    let amount: u64 = text[..text.len() - 1].parse().ok()?;
+    Some(Travel::Time(Duration::from_secs(amount.checked_mul(unit)?)))
+}
+
+// Parse the command line `input`, or return an error message.
+fn parse(input: &str) -> Result<NamedCommand, String> {
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let command = match words.as_slice() {
+        ["sort"] => NamedCommand::Sort,
+        [name @ ("export" | "export!"), format, path] => {
+            let format = match *format {
+                "md" => ExportFormat::Markdown,
+                "txt" => ExportFormat::Indented,
+                _ => return Err(format!("Unknown format: {format}")),
+            };
+            NamedCommand::Export(format, path.to_string(), *name == "export!")
+        }
+        ["saveas", filename] => NamedCommand::SaveAs(filename.to_string()),
+        ["goto", path] => match parse_path(path) {
+            Some(path) => NamedCommand::Goto(path),
+            None => return Err(format!("Invalid path: {path}")),
+        },
+        ["fold", "all"] => NamedCommand::FoldAll(true),
+        ["fold", "none"] => NamedCommand::FoldAll(false),
+        ["earlier", travel] => match parse_travel(travel) {
+            Some(travel) => NamedCommand::Earlier(travel),
+            None => return Err(format!("Invalid time: {travel}")),
+        },
+        ["later", travel] => match parse_travel(travel) {
+            Some(travel) => NamedCommand::Later(travel),
+            None => return Err(format!("Invalid time: {travel}")),
+        },
+        [name] => match NormalMsg::ALL.iter().find(|msg| msg.name() == *name) {
+            Some(&msg) => NamedCommand::Action(msg),
+            None if COMMANDS.contains(name) => {
+                return Err(format!("Invalid arguments: {name}"));
+            }
+            None => return Err(format!("Unknown command: {name}")),
+        },
+        [name, ..] if COMMANDS.contains(name) => {
+            return Err(format!("Invalid arguments: {name}"));
+        }
+        [name, ..] => return Err(format!("Unknown command: {name}")),
+        [] => return Err(String::from("Empty command")),
+    };
+    Ok(command)
+}
+
+/// Return the candidates completing the last word of the command line.
+pub fn completions(input: &str) -> Vec<&'static str> {
+    let words: Vec<&str> = input.split(' ').collect();
+    let (word, candidates): (&str, Vec<&'static str>) = match words.as_slice() {
+        [word] => {
+            let actions = NormalMsg::ALL.iter().map(|msg| msg.name());
+            let mut names: Vec<_> =
+                COMMANDS.into_iter().chain(actions).collect();
+            names.sort_unstable();
+            names.dedup();
+            (word, names)
+        }
+        ["export" | "export!", word] => (word, EXPORT_FORMATS.to_vec()),
+        ["fold", word] => (word, FOLD_TARGETS.to_vec()),
+        _ => return Vec::new(),
+    };
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(word))
+        .collect()
+}
+
+/// Run the command line `input` on the `session`.
+pub fn run(input: &str, session: SessionState) -> Command {
+    let command = match parse(input) {
+        Ok(command) => command,
+        Err(alert) => {
+            return Command::None(Model::Normal(session.set_alert(alert)));
+        }
+    };
+    // Saving is refused too, since a read-only session has no data file.
+    let is_write = match &command {
+        NamedCommand::Action(msg) => msg.is_edit(),
+        NamedCommand::Sort
+        | NamedCommand::SaveAs(_)
+        | NamedCommand::Earlier(_)
+        | NamedCommand::Later(_) => true,
+        _ => false,
+    };
+    if session.read_only && is_write {
+        let session = session.set_alert("Read only");
+        return Command::None(Model::Normal(session));
+    }
+    let session = match command {
+        NamedCommand::Action(msg) => return update_normal(msg, session),
+        NamedCommand::Sort => session.map_focus(FocusNode::sort_siblings),
+        NamedCommand::Export(format, path, overwrite) => {
+            return Command::Export {
+                format,
+                path,
+                overwrite,
+                state: session,
+            };
+        }
+        NamedCommand::SaveAs(filename) => {
+            return Command::SaveAs(filename, session);
+        }
+        NamedCommand::Goto(path) => session.jump_to_path(&path),
+        NamedCommand::FoldAll(folded) => {
+            session.navigate(|focus| focus.fold_all(folded))
+        }
+        NamedCommand::Earlier(Travel::Steps(count)) => session.older(count),
+        NamedCommand::Earlier(Travel::Time(duration)) => {
+            session.earlier(duration)
+        }
+        NamedCommand::Later(Travel::Steps(count)) => session.newer(count),
+        NamedCommand::Later(Travel::Time(duration)) => session.later(duration),
+    };
+    Command::None(Model::Normal(session))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(input: &str) -> String {
+        match parse(input) {
+            Ok(_) => panic!("Expected `{input}` to fail"),
+            Err(error) => error,
+        }
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(parse_path("1"), Some(vec![0]));
+        assert_eq!(parse_path("3.2.1"), Some(vec![2, 1, 0]));
+        assert_eq!(parse_path("0"), None);
+        assert_eq!(parse_path("2.0"), None);
+        assert_eq!(parse_path("3."), None);
+        assert_eq!(parse_path(".3"), None);
+        assert_eq!(parse_path("-1"), None);
+        assert_eq!(parse_path("a"), None);
+    }
+
+    #[test]
+    fn travels() {
+        let time = |text| match parse_travel(text) {
+            Some(Travel::Time(duration)) => Some(duration.as_secs()),
+            _ => None,
+        };
+        let steps = |text| match parse_travel(text) {
+            Some(Travel::Steps(count)) => Some(count),
+            _ => None,
+        };
+        assert_eq!(steps("0"), Some(0));
+        assert_eq!(steps("12"), Some(12));
+        assert_eq!(time("10s"), Some(10));
+        assert_eq!(time("10m"), Some(600));
+        assert_eq!(time("2h"), Some(7200));
+        assert_eq!(time("1d"), Some(86400));
+        assert_eq!(time("0m"), Some(0));
+        assert!(parse_travel("10x").is_none());
+        assert!(parse_travel("m").is_none());
+        assert!(parse_travel("1.5h").is_none());
+        assert!(parse_travel("-1").is_none());
+        assert!(parse_travel("").is_none());
+        assert!(parse_travel(&format!("{}d", u64::MAX)).is_none());
+    }
+
+    #[test]
+    fn commands() {
+        assert!(matches!(parse("  sort "), Ok(NamedCommand::Sort)));
+        assert!(matches!(
+            parse("export md out.md"),
+            Ok(NamedCommand::Export(ExportFormat::Markdown, path, false))
+                if path == "out.md"
+        ));
+        assert!(matches!(
+            parse("export! txt out.txt"),
+            Ok(NamedCommand::Export(ExportFormat::Indented, _, true))
+        ));
+        assert!(matches!(
+            parse("goto 3.2"),
+            Ok(NamedCommand::Goto(path)) if path == [2, 1]
+        ));
+        assert!(matches!(
+            parse("fold none"),
+            Ok(NamedCommand::FoldAll(false))
+        ));
+        assert!(matches!(
+            parse("earlier 10m"),
+            Ok(NamedCommand::Earlier(Travel::Time(_)))
+        ));
+        assert!(matches!(
+            parse("later 2"),
+            Ok(NamedCommand::Later(Travel::Steps(2)))
+        ));
+        assert!(matches!(
+            parse("saveas notes"),
+            Ok(NamedCommand::SaveAs(name)) if name == "notes"
+        ));
+        assert!(matches!(parse("undo"), Ok(NamedCommand::Action(_))));
+    }
+
+    #[test]
+    fn command_errors() {
+        assert_eq!(error(""), "Empty command");
+        assert_eq!(error("frobnicate"), "Unknown command: frobnicate");
+        assert_eq!(error("frobnicate now"), "Unknown command: frobnicate");
+        assert_eq!(error("export pdf out.pdf"), "Unknown format: pdf");
+        assert_eq!(error("goto 0"), "Invalid path: 0");
+        assert_eq!(error("goto 3."), "Invalid path: 3.");
+        assert_eq!(error("earlier 10x"), "Invalid time: 10x");
+        assert_eq!(error("later 3."), "Invalid time: 3.");
+        assert_eq!(error("goto"), "Invalid arguments: goto");
+        assert_eq!(error("fold some"), "Invalid arguments: fold");
+        assert_eq!(error("saveas a b"), "Invalid arguments: saveas");
+    }
+}
@@ -0,0 +1,83 @@
+use crate::{
+    io::Command,
+    message::{ConfirmMsg, LabelMsg, Message, PasteMsg},
+    model::{
+        ConfirmState, EditStep, Model, PasteState, SessionState, VisualState,
+        text_input::TextInput,
+    },
+    update::update,
+};
+
+// Convert an edit `step` into a Message for the `model`, continuing with the
+// model unchanged if the step does not apply to its mode.
+fn to_message(step: &EditStep, model: Model) -> Message {
+    match (step, model) {
+        (EditStep::Normal(msg), Model::Normal(state)) => {
+            Message::Normal(*msg, state)
+        }
+        (EditStep::Insert(msg), Model::Insert(state)) => {
+            Message::Insert(*msg, state)
+        }
+        (EditStep::Move(msg), Model::Move(state)) => Message::Move(*msg, state),
+        (EditStep::Visual(msg, len), Model::Normal(state)) => {
+            Message::Visual(*msg, VisualState::new(state, *len))
+        }
+        (EditStep::Label(label), Model::LabelInput(mut label_state)) => {
+            label_state.input = TextInput::from(label.clone());
+            Message::LabelInput(LabelMsg::Submit, label_state)
+        }
+        (EditStep::Paste(text, as_outline), Model::LabelInput(label_state)) => {
+            let paste_state = PasteState {
+                as_outline: *as_outline,
+                text: text.clone(),
+                label_state,
+            };
+            Message::Paste(PasteMsg::Confirm, paste_state)
+        }
+        (EditStep::Confirm, Model::Confirm(confirm_state)) => {
+            Message::Confirm(ConfirmMsg::Confirm, confirm_state)
+        }
+        (_, model) => Message::Continue(model),
+    }
+}
+
+// Return the session of a `model` left partway through an edit, discarding
+// the unfinished edit.
+fn into_session(model: Model) -> SessionState {
+    match model {
+        Model::Normal(state) | Model::Insert(state) | Model::Move(state) => {
+            state
+        }
+        Model::Visual(visual_state) => visual_state.session,
+        Model::LabelInput(label_state) => label_state.fallback(),
+        Model::Paste(paste_state) => paste_state.label_state.fallback(),
+        Model::Confirm(ConfirmState::DeleteItem(state)) => *state,
+        _ => unreachable!("Edits only pass through session modes"),
+    }
+}
+
+// Apply the `steps` of an edit to the `state`, stopping at the first step
+// that does not apply.
+fn replay_once(steps: &[EditStep], state: SessionState) -> SessionState {
+    let mut model = Model::Normal(state);
+    for step in steps {
+        let message = match to_message(step, model) {
+            Message::Continue(model) => return into_session(model),
+            message => message,
+        };
+        model = match update(message) {
+            Command::None(model) => model,
+            _ => unreachable!("Edits only update the model"),
+        };
+    }
+    into_session(model)
+}
+
+/// Repeat the last edit `count` times at the focused node as a single change.
+pub fn replay(count: usize, state: SessionState) -> SessionState {
+    let steps = state.last_edit.clone();
+    let checkpoint = state.history.checkpoint();
+    let before = state.forest.clone();
+    let state = (0..count).fold(state, |state, _| replay_once(&steps, state));
+    state.squash_history(checkpoint, before)
+}
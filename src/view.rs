@@ -1,8 +1,9 @@
 mod cmdbar;
 mod forest;
+mod help;
 mod scroll;
 mod statusbar;
-mod style;
+pub mod style;
 
 use std::cmp::min;
 
@@ -10,14 +11,14 @@ use ratatui::{
     Frame,
     layout::{Constraint, Layout},
     prelude::Rect,
-    style::{Style, Styled},
-    text::{Line, Text},
+    style::{Style, Styled, Stylize},
+    text::{Line, Span, Text},
     widgets::{Block, Borders, Padding, Paragraph, Wrap},
 };
 
 use crate::{
-    model::{ConfirmState, LoadState, Model},
-    view::scroll::{ScrollArea, ScrollContent},
+    model::{ConfirmState, LoadState, Model, text_input::TextInput},
+    view::scroll::{ScrollArea, ScrollContent, Viewport, scroll_start},
 };
 
 use self::{cmdbar::command_bar, statusbar::status_bar};
@@ -25,6 +26,51 @@ use self::{cmdbar::command_bar, statusbar::status_bar};
 const INDENT: &str = "  ";
 const SCROLL_HINT: &str = "  ...";
 
+/// An item that can be clicked in the main area.
+#[derive(Clone, PartialEq, Eq)]
+pub enum ClickTarget {
+    Node(Vec<usize>),
+    File(usize),
+}
+
+/// The clickable items rendered on each row of the last frame, the index of
+/// the first row of the selected one, and the scroll position of the list
+/// they were taken from.
+#[derive(Default)]
+pub struct ClickMap {
+    top: u16,
+    height: usize,
+    start: usize,
+    targets: Vec<ClickTarget>,
+    selected: Option<usize>,
+}
+
+impl ClickMap {
+    /// Return the number of rows in the scrolled area.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Return the item rendered on the screen `row`, if any.
+    pub fn at_row(&self, row: u16) -> Option<&ClickTarget> {
+        let index = row.checked_sub(self.top)?;
+        self.targets.get(index as usize)
+    }
+
+    /// Return the item rendered after or before the selected one, if any.
+    pub fn adjacent(&self, forward: bool) -> Option<&ClickTarget> {
+        let selected = self.selected?;
+        let current = self.targets.get(selected)?;
+        if forward {
+            self.targets[selected..]
+                .iter()
+                .find(|&target| target != current)
+        } else {
+            self.targets[..selected].last()
+        }
+    }
+}
+
 // Start index and indicators used for scrolling through a file list.
 struct ScrollInfo {
     start: usize,
@@ -32,14 +78,16 @@ struct ScrollInfo {
     more_below: bool,
 }
 
+// Scroll as little as possible from the `previous` start to keep the item at
+// `index` within the scrolloff margin.
 fn compute_scroll_info(
     area_height: usize,
     list_size: usize,
     index: usize,
+    previous: usize,
 ) -> ScrollInfo {
-    let centered = index.saturating_sub(area_height / 2);
     let max_start = list_size.saturating_sub(area_height);
-    let start = min(centered, max_start);
+    let start = min(scroll_start(previous, index, 1, area_height), max_start);
     ScrollInfo {
         start,
         more_above: start > 0,
@@ -63,7 +111,7 @@ fn pad_main_paragraph(text: Text, padding: Padding) -> Paragraph {
     Paragraph::new(text)
         .block(block)
         .left_aligned()
-        .set_style(style::DEFAULT)
+        .set_style(style::theme().default)
 }
 
 // Style the `text` to display in the main area.
@@ -74,31 +122,36 @@ fn main_paragraph(text: Text) -> Paragraph {
 fn load<'a>(
     load_state: &'a LoadState,
     highlight: Style,
-) -> ScrollArea<'a, impl FnOnce(usize) -> ScrollContent<'a> + 'a> {
-    let build = move |area_height| {
+) -> ScrollArea<'a, impl FnOnce(Viewport) -> ScrollContent<'a> + 'a> {
+    let build = move |viewport: Viewport| {
+        let area_height = viewport.height;
         let size = load_state.files.len();
         let index = load_state.index;
         let ScrollInfo {
             start,
             more_above,
             more_below,
-        } = compute_scroll_info(area_height, size, index);
+        } = compute_scroll_info(area_height, size, index, viewport.start);
         let end = std::cmp::min(start + area_height, size);
         let selected = index - start;
+        let targets = (start..end).map(ClickTarget::File).collect();
         let lines = load_state.files[start..end].iter().enumerate().map(
             |(i, entry)| {
                 let line_style = if i == selected {
                     highlight
                 } else {
-                    style::DEFAULT
+                    style::theme().default
                 };
                 Line::styled(format!("{INDENT}{}", entry.name), line_style)
             },
         );
         ScrollContent {
             text: Text::from_iter(lines),
+            start,
             more_above,
             more_below,
+            targets,
+            selected: Some(selected),
         }
     };
     ScrollArea { build }
@@ -107,24 +160,36 @@ fn load<'a>(
 // Return Load mode widget with normal highlight.
 fn load_normal<'a>(
     load_state: &'a LoadState,
-) -> ScrollArea<'a, impl FnOnce(usize) -> ScrollContent<'a> + 'a> {
-    load(load_state, style::DEFAULT_HL)
+) -> ScrollArea<'a, impl FnOnce(Viewport) -> ScrollContent<'a> + 'a> {
+    load(load_state, style::theme().default_hl)
 }
 
 // Return Load mode widget with highlight for deletion.
 fn load_delete<'a>(
     load_state: &'a LoadState,
-) -> ScrollArea<'a, impl FnOnce(usize) -> ScrollContent<'a> + 'a> {
-    load(load_state, style::DELETE)
+) -> ScrollArea<'a, impl FnOnce(Viewport) -> ScrollContent<'a> + 'a> {
+    load(load_state, style::theme().delete)
 }
 
-// Return the text input widget given the `input` string.
-fn text_input(input: &str) -> Paragraph<'static> {
-    let content = format!("❯ {input}").into();
-    let cursor = "█".set_style(style::CURSOR);
-    let text = Line::from(vec![content, cursor])
-        .set_style(style::DEFAULT)
-        .into();
+// Return spans showing the `input` text with its cursor, reversing the
+// grapheme under the cursor or showing the `end_cursor` after the text.
+fn input_spans<'a>(
+    input: &'a TextInput,
+    end_cursor: Span<'a>,
+) -> Vec<Span<'a>> {
+    let (before, under, after) = input.split_at_cursor();
+    let cursor = match under {
+        Some(grapheme) => grapheme.reversed(),
+        None => end_cursor,
+    };
+    vec![before.into(), cursor, after.into()]
+}
+
+// Return the text input widget given the `input` text.
+fn text_input(input: &TextInput) -> Paragraph<'_> {
+    let mut spans = vec![Span::raw("❯ ")];
+    spans.extend(input_spans(input, "█".set_style(style::theme().cursor)));
+    let text = Line::from(spans).set_style(style::theme().default).into();
     main_paragraph(text).wrap(Wrap { trim: false })
 }
 
@@ -133,32 +198,53 @@ fn save_query(save: bool) -> Paragraph<'static> {
     let line1 = Line::from(" Save ");
     let line2 = Line::from(" Discard Changes ");
     let lines = match save {
-        true => vec![line1.set_style(style::DEFAULT_HL), line2],
-        false => vec![line1, line2.set_style(style::DEFAULT_HL)],
+        true => vec![line1.set_style(style::theme().default_hl), line2],
+        false => vec![line1, line2.set_style(style::theme().default_hl)],
     };
     main_paragraph(Text::from(lines))
 }
 
-/// Render the UI on the `frame` based on the current `model`.
-pub fn view(model: &Model, frame: &mut Frame) {
-    let [status_bar_area, main_area, command_bar_area] =
-        top_mid_bottom(frame.area());
-    frame.render_widget(status_bar(model), status_bar_area);
+// Return the paste query widget.
+fn paste_query(as_outline: bool) -> Paragraph<'static> {
+    let line1 = Line::from(" One item per line ");
+    let line2 = Line::from(" Outline from indentation ");
+    let lines = match as_outline {
+        false => vec![line1.set_style(style::theme().default_hl), line2],
+        true => vec![line1, line2.set_style(style::theme().default_hl)],
+    };
+    main_paragraph(Text::from(lines))
+}
+
+// Render the main area of the `model` on the `frame`, storing the items that
+// can be clicked in the `click_map`.
+fn render_main(
+    model: &Model,
+    frame: &mut Frame,
+    main_area: Rect,
+    click_map: &mut ClickMap,
+) {
     match model {
-        Model::Load(load_state) => {
-            frame.render_widget(load_normal(load_state), main_area)
-        }
+        Model::Load(load_state) => frame.render_stateful_widget(
+            load_normal(load_state),
+            main_area,
+            click_map,
+        ),
         Model::Normal(state) => {
             let forest = forest::normal(state.focus());
-            frame.render_widget(forest, main_area);
+            frame.render_stateful_widget(forest, main_area, click_map);
         }
         Model::Insert(state) => {
             let forest = forest::insert(state.focus());
-            frame.render_widget(forest, main_area);
+            frame.render_stateful_widget(forest, main_area, click_map);
         }
         Model::Move(state) => {
             let forest = forest::move_mode(state.focus());
-            frame.render_widget(forest, main_area);
+            frame.render_stateful_widget(forest, main_area, click_map);
+        }
+        Model::Visual(visual_state) => {
+            let focus = visual_state.session.focus();
+            let forest = forest::visual(focus, visual_state.range());
+            frame.render_stateful_widget(forest, main_area, click_map);
         }
         Model::Save(save_state) => {
             frame.render_widget(save_query(save_state.save), main_area)
@@ -166,11 +252,34 @@ pub fn view(model: &Model, frame: &mut Frame) {
         Model::LabelInput(label_state) => {
             let focus = label_state.session.focus();
             let forest = forest::input(focus, &label_state.input);
-            frame.render_widget(forest, main_area);
+            frame.render_stateful_widget(forest, main_area, click_map);
+        }
+        Model::Paste(paste_state) => {
+            frame.render_widget(paste_query(paste_state.as_outline), main_area)
         }
         Model::FilenameInput(filename_state) => {
             frame.render_widget(text_input(&filename_state.input), main_area)
         }
+        Model::CommandLine(command_line_state) => {
+            let forest = forest::normal(command_line_state.session.focus());
+            frame.render_stateful_widget(forest, main_area, click_map);
+        }
+        Model::Search(search_state) => {
+            let forest = forest::normal(search_state.session.focus());
+            frame.render_stateful_widget(forest, main_area, click_map);
+        }
+        Model::Mark(mark_state) => {
+            let forest = forest::normal(mark_state.session.focus());
+            frame.render_stateful_widget(forest, main_area, click_map);
+        }
+        Model::Help(help_state) => {
+            render_main(&help_state.model, frame, main_area, click_map);
+            let start = click_map.start;
+            let popup = help::help(help_state);
+            frame.render_stateful_widget(popup, main_area, click_map);
+            // Keep the scroll position of the view behind the popup.
+            click_map.start = start;
+        }
         Model::Confirm(confirm_state) => match confirm_state {
             ConfirmState::NewSession => {
                 let empty = main_paragraph(Text::default());
@@ -178,12 +287,33 @@ pub fn view(model: &Model, frame: &mut Frame) {
             }
             ConfirmState::DeleteItem(state) => {
                 let forest = forest::delete(state.focus());
-                frame.render_widget(forest, main_area);
-            }
-            ConfirmState::DeleteFile(load_state) => {
-                frame.render_widget(load_delete(load_state), main_area)
+                frame.render_stateful_widget(forest, main_area, click_map);
             }
+            ConfirmState::DeleteFile(load_state) => frame
+                .render_stateful_widget(
+                    load_delete(load_state),
+                    main_area,
+                    click_map,
+                ),
         },
     }
+}
+
+/// Render the UI on the `frame` based on the current `model`, returning the
+/// items that can be clicked. Scrolled views continue from their position in
+/// the `last` frame.
+pub fn view(model: &Model, frame: &mut Frame, last: &ClickMap) -> ClickMap {
+    let [status_bar_area, main_area, command_bar_area] =
+        top_mid_bottom(frame.area());
+    let mut click_map = ClickMap {
+        start: last.start,
+        ..ClickMap::default()
+    };
+    frame.render_widget(
+        status_bar(model, status_bar_area.width),
+        status_bar_area,
+    );
+    render_main(model, frame, main_area, &mut click_map);
     frame.render_widget(command_bar(model), command_bar_area);
+    click_map
 }
@@ -1,136 +1,271 @@
 use ratatui::{style::Styled, text::Line};
 
 use crate::{
-    model::{ConfirmState, FilenameState, LabelState, Model, SessionState},
+    message::{
+        ConfirmMsg, HelpMsg, InsertMsg, LoadMsg, MoveMsg, NormalMsg, PasteMsg,
+        SaveMsg, VisualMsg,
+        keymap::{self, Action, CommandAction, InputAction, ModeKeymap},
+    },
+    model::{ConfirmState, Model, SessionState},
     view::style,
 };
 
-type KeyPair<'a> = (&'a str, &'a str);
+type KeyPair = (String, &'static str);
 
-// Multimode
-const CANCEL_SPACE: KeyPair = ("Space", "Cancel");
-const CANCEL_TAB: KeyPair = ("Tab", "Cancel");
-const SUBMIT: KeyPair = ("Enter", "Submit");
-const DELETE: KeyPair = ("D", "Delete");
-const QUIT: KeyPair = ("Q", "Quit");
-
-// Load Mode
-const LOAD_NAVIGATE: KeyPair = ("JK │ ↓↑", "Navigate");
-const OPEN: KeyPair = ("Enter", "Open");
-const NEW: KeyPair = ("N", "New");
-const RENAME: KeyPair = ("R", "Rename");
+// Load mode
+const LOAD_NAVIGATE: [LoadMsg; 2] = [LoadMsg::Increment, LoadMsg::Decrement];
+const LOAD_COMMANDS: [LoadMsg; 5] = [
+    LoadMsg::Open,
+    LoadMsg::New,
+    LoadMsg::Rename,
+    LoadMsg::Delete,
+    LoadMsg::Quit,
+];
 
 // Normal mode
-const NAVIGATE: KeyPair = ("HJKL │ ←↓↑→", "Navigate");
-const EDIT: KeyPair = ("E", "Edit");
-const MOVE: KeyPair = ("M", "Move");
-const NEST: KeyPair = ("N", "Nest");
-const FLATTEN: KeyPair = ("F", "Flatten");
-const INSERT: KeyPair = ("I", "Insert");
-const UNDO: KeyPair = ("U", "Undo");
-const REDO: KeyPair = ("R", "Redo");
-
-// Insert
-const PARENT: KeyPair = ("H", "Parent");
-const CHILD: KeyPair = ("L", "Child");
-const BEFORE: KeyPair = ("K", "Before");
-const AFTER: KeyPair = ("J", "After");
-
-// Move
-const DOWN: KeyPair = ("J │ ↓", "Down");
-const UP: KeyPair = ("K │ ↑", "Up");
-const PROMOTE: KeyPair = ("H │ ←", "Promote");
-const DEMOTE: KeyPair = ("L │ →", "Demote");
-const DONE: KeyPair = ("Space", "Done");
-
-// Save
-const TOGGLE: KeyPair = ("J", "Toggle");
-
-// Confirm mode
-const CONFIRM: KeyPair = ("Enter", "Confirm");
+const NAVIGATE: [NormalMsg; 4] = [
+    NormalMsg::Ascend,
+    NormalMsg::Next,
+    NormalMsg::Previous,
+    NormalMsg::Descend,
+];
+const EDIT_COMMANDS: [NormalMsg; 7] = [
+    NormalMsg::Edit,
+    NormalMsg::Move,
+    NormalMsg::Visual,
+    NormalMsg::Nest,
+    NormalMsg::Flatten,
+    NormalMsg::Insert,
+    NormalMsg::Delete,
+];
+const MATCH: [NormalMsg; 2] = [NormalMsg::PrevMatch, NormalMsg::NextMatch];
+
+// Insert mode
+const INSERT_COMMANDS: [InsertMsg; 5] = [
+    InsertMsg::Parent,
+    InsertMsg::Child,
+    InsertMsg::Before,
+    InsertMsg::After,
+    InsertMsg::Cancel,
+];
+
+// Move mode
+const MOVE_COMMANDS: [MoveMsg; 5] = [
+    MoveMsg::Forward,
+    MoveMsg::Backward,
+    MoveMsg::Promote,
+    MoveMsg::Demote,
+    MoveMsg::Done,
+];
+
+// Visual mode
+const VISUAL_COMMANDS: [VisualMsg; 11] = [
+    VisualMsg::Next,
+    VisualMsg::Previous,
+    VisualMsg::Forward,
+    VisualMsg::Backward,
+    VisualMsg::Promote,
+    VisualMsg::Demote,
+    VisualMsg::Wrap,
+    VisualMsg::Yank,
+    VisualMsg::Delete,
+    VisualMsg::Sort,
+    VisualMsg::Cancel,
+];
+
+// Paste mode
+const PASTE_COMMANDS: [PasteMsg; 3] =
+    [PasteMsg::Toggle, PasteMsg::Confirm, PasteMsg::Cancel];
+
+// Help overlay
+const HELP_COMMANDS: [HelpMsg; 5] = [
+    HelpMsg::Down,
+    HelpMsg::Up,
+    HelpMsg::PageDown,
+    HelpMsg::PageUp,
+    HelpMsg::Close,
+];
+
+// Command line mode
+const COMMAND_LINE_COMMANDS: [CommandAction; 5] = [
+    CommandAction::Submit,
+    CommandAction::Complete,
+    CommandAction::Older,
+    CommandAction::Newer,
+    CommandAction::Cancel,
+];
+
+// Save mode
+const SAVE_COMMANDS: [SaveMsg; 3] =
+    [SaveMsg::Toggle, SaveMsg::Confirm, SaveMsg::Cancel];
+
+// Format the keys bound to a group of actions, e.g. `HJKL │ ←↓↑→`.
+fn format_keys<A: Action>(keymap: &ModeKeymap<A>, actions: &[A]) -> String {
+    let mut chars = String::new();
+    let mut named = Vec::new();
+    for &action in actions {
+        for key in keymap.keys(action) {
+            match key.display() {
+                (text, true) => chars.push_str(&text),
+                (text, false) => named.push(text),
+            }
+        }
+    }
+    let separator = if named.iter().all(|text| text.chars().count() == 1) {
+        ""
+    } else {
+        "/"
+    };
+    [chars, named.join(separator)]
+        .into_iter()
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" │ ")
+}
+
+// Return the key-command pairs for the bound `actions`, grouping
+// consecutive actions that share a label.
+fn to_pairs<A: Action>(keymap: &ModeKeymap<A>, actions: &[A]) -> Vec<KeyPair> {
+    actions
+        .chunk_by(|a, b| a.label() == b.label())
+        .filter_map(|group| {
+            let keys = format_keys(keymap, group);
+            (!keys.is_empty()).then(|| (keys, group[0].label()))
+        })
+        .collect()
+}
 
 // Return the load mode key-command pairs.
-fn load_mode_commands(file_count: usize) -> Vec<KeyPair<'static>> {
-    let mut pairs = Vec::new();
+fn load_mode_commands(file_count: usize) -> Vec<KeyPair> {
+    let mut actions = Vec::new();
     if file_count > 1 {
-        pairs.push(LOAD_NAVIGATE);
+        actions.extend(LOAD_NAVIGATE);
     }
-    pairs.extend(&[OPEN, NEW, RENAME, DELETE, QUIT]);
+    actions.extend(LOAD_COMMANDS);
+    let mut pairs = to_pairs(&keymap::get().load, &actions);
+    pairs.extend(help_command());
     pairs
 }
 
 // Return the normal mode key-command pairs.
-fn normal_mode_commands(session: &SessionState) -> Vec<KeyPair<'static>> {
-    let mut pairs = Vec::new();
-    if session.focus().is_none() {
-        pairs.push(INSERT);
-    } else {
-        pairs.extend(&[NAVIGATE, EDIT, MOVE, NEST, FLATTEN, INSERT, DELETE]);
+fn normal_mode_commands(session: &SessionState) -> Vec<KeyPair> {
+    let mut actions = Vec::new();
+    if session.focus().is_some() {
+        actions.extend(NAVIGATE);
+        if !session.read_only {
+            actions.extend(EDIT_COMMANDS);
+        }
+        actions.extend([NormalMsg::Fold, NormalMsg::Hoist]);
+        if session.hoist.is_some() {
+            actions.extend([NormalMsg::Unhoist, NormalMsg::UnhoistAll]);
+        }
+        actions.extend([NormalMsg::Search, NormalMsg::SetMark]);
+        if !session.marks.is_empty() {
+            actions.push(NormalMsg::JumpMark);
+        }
+        if !session.jumps.is_empty() {
+            actions.extend([NormalMsg::JumpBack, NormalMsg::JumpForward]);
+        }
+    } else if !session.read_only {
+        actions.push(NormalMsg::Insert);
     }
-    if !session.undo_stack.is_empty() {
-        pairs.push(UNDO);
+    if session.search.is_some() {
+        actions.extend(MATCH);
     }
-    if !session.redo_stack.is_empty() {
-        pairs.push(REDO);
+    if !session.read_only {
+        if !session.yank.is_empty() {
+            actions.push(NormalMsg::Put);
+        }
+        if session.history.can_undo() {
+            actions.push(NormalMsg::Undo);
+        }
+        if session.history.can_redo() {
+            actions.push(NormalMsg::Redo);
+        }
+        if session.history.len() > 1 {
+            actions.extend([NormalMsg::Older, NormalMsg::Newer]);
+        }
+        if !session.last_edit.is_empty() && session.focus().is_some() {
+            actions.push(NormalMsg::Repeat);
+        }
     }
-    pairs.push(QUIT);
+    actions.extend([NormalMsg::CommandLine, NormalMsg::Quit]);
+    let mut pairs = to_pairs(&keymap::get().normal, &actions);
+    pairs.extend(help_command());
     pairs
 }
 
-// Return the input mode key-command pairs.
-fn label_input_commands(label_state: &LabelState) -> Vec<KeyPair<'static>> {
-    if label_state.input.is_empty() {
-        vec![CANCEL_TAB]
-    } else {
-        vec![SUBMIT, CANCEL_TAB]
-    }
+// Return the key-command pair opening the help overlay, if it is bound.
+fn help_command() -> Vec<KeyPair> {
+    to_pairs(&keymap::get().help, &[HelpMsg::Toggle])
 }
 
-// Return the input mode key-command pairs.
-fn filename_input_commands(
-    filename_state: &FilenameState,
-) -> Vec<KeyPair<'static>> {
-    if filename_state.is_valid() {
-        vec![SUBMIT, CANCEL_TAB]
+// Return the text input key-command pairs.
+fn input_commands(can_submit: bool) -> Vec<KeyPair> {
+    let actions: &[InputAction] = if can_submit {
+        &[InputAction::Submit, InputAction::Cancel]
     } else {
-        vec![CANCEL_TAB]
-    }
+        &[InputAction::Cancel]
+    };
+    to_pairs(&keymap::get().input, actions)
+}
+
+// Return the mark mode key-command pairs, where any other key cancels.
+fn mark_mode_commands() -> Vec<KeyPair> {
+    vec![
+        (String::from("Letter"), "Mark"),
+        (String::from("Other"), "Cancel"),
+    ]
 }
 
 // Return the confirm mode key-command pairs.
-fn confirm_mode_commands(
-    confirm_state: &ConfirmState,
-) -> Vec<KeyPair<'static>> {
-    match confirm_state {
-        ConfirmState::NewSession => vec![CONFIRM],
-        _ => vec![CONFIRM, CANCEL_SPACE],
-    }
+fn confirm_mode_commands(confirm_state: &ConfirmState) -> Vec<KeyPair> {
+    let actions: &[ConfirmMsg] = match confirm_state {
+        ConfirmState::NewSession => &[ConfirmMsg::Confirm],
+        _ => &[ConfirmMsg::Confirm, ConfirmMsg::Cancel],
+    };
+    to_pairs(&keymap::get().confirm, actions)
 }
 
 // Construct the command bar widget from a sequence of key-command pairs.
-fn to_command_bar(pairs: Vec<KeyPair>) -> Line {
+fn to_command_bar(pairs: Vec<KeyPair>) -> Line<'static> {
     let mut spans = Vec::new();
     for (key, command) in pairs {
-        spans.push(format!(" {key} ").set_style(style::CMD_KEY));
-        spans.push(format!(" {command}").set_style(style::CMD_NAME));
+        spans.push(format!(" {key} ").set_style(style::theme().cmd_key));
+        spans.push(format!(" {command}").set_style(style::theme().cmd_name));
         spans.push("    ".into());
     }
     spans.pop(); // Remove extra spacer at end
-    Line::from(spans).centered().set_style(style::ACCENT)
+    Line::from(spans)
+        .centered()
+        .set_style(style::theme().accent)
 }
 
 /// Return the command bar widget based on the current `model`.
 pub fn command_bar(model: &Model) -> Line<'static> {
+    let keymap = keymap::get();
     let pairs = match model {
         Model::Load(load_state) => load_mode_commands(load_state.files.len()),
         Model::Normal(state) => normal_mode_commands(state),
-        Model::Insert(_) => vec![PARENT, CHILD, BEFORE, AFTER, CANCEL_SPACE],
-        Model::Move(_) => vec![DOWN, UP, PROMOTE, DEMOTE, DONE],
-        Model::Save(_) => vec![TOGGLE, CONFIRM, CANCEL_SPACE],
-        Model::LabelInput(label_state) => label_input_commands(label_state),
+        Model::Insert(_) => to_pairs(&keymap.insert, &INSERT_COMMANDS),
+        Model::Move(_) => to_pairs(&keymap.move_mode, &MOVE_COMMANDS),
+        Model::Visual(_) => to_pairs(&keymap.visual, &VISUAL_COMMANDS),
+        Model::Save(_) => to_pairs(&keymap.save, &SAVE_COMMANDS),
+        Model::Paste(_) => to_pairs(&keymap.paste, &PASTE_COMMANDS),
+        Model::LabelInput(label_state) => {
+            input_commands(!label_state.input.is_empty())
+        }
         Model::FilenameInput(filename_state) => {
-            filename_input_commands(filename_state)
+            input_commands(filename_state.is_valid())
+        }
+        Model::Search(search_state) => {
+            input_commands(!search_state.input.is_empty())
+        }
+        Model::CommandLine(_) => {
+            to_pairs(&keymap.command, &COMMAND_LINE_COMMANDS)
         }
+        Model::Mark(_) => mark_mode_commands(),
+        Model::Help(_) => to_pairs(&keymap.help, &HELP_COMMANDS),
         Model::Confirm(confirm_state) => confirm_mode_commands(confirm_state),
     };
     to_command_bar(pairs)
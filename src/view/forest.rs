@@ -1,11 +1,18 @@
-use std::{borrow::Cow, collections::VecDeque};
+use std::{
+    borrow::Cow,
+    cmp::{max, min},
+    collections::VecDeque,
+    ops::Range,
+};
 
 use ratatui::text::{Line, Span, Text};
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::{
+    model::{SiblingRange, text_input::TextInput},
     view::{
-        INDENT,
-        scroll::{ScrollArea, ScrollContent},
+        ClickTarget, INDENT, input_spans,
+        scroll::{ScrollArea, ScrollContent, Viewport, scroll_start},
         style,
     },
     zipper::{
@@ -14,22 +21,43 @@ use crate::{
     },
 };
 
+const FOLD_HINT: &str = " …";
+// Replaces INDENT on rows whose leading indentation is elided.
+const ELIDED_INDENT: &str = "‹ ";
+// The width in columns of each level of indentation.
+const INDENT_BLOCK_WIDTH: usize = 3;
+
 // Represents a text block used for tree drawing.
 enum IndentBlock {
     Spacer,
     VertBar,
 }
 
-// Data used to render a single forest line in the TUI.
+impl IndentBlock {
+    fn as_str(&self) -> &'static str {
+        match self {
+            IndentBlock::Spacer => "   ",
+            IndentBlock::VertBar => "│  ",
+        }
+    }
+}
+
+// Data used to render a single forest node in the TUI, with the prefix of
+// the continuation lines of a wrapped label.
 struct LineContent<'a> {
     tree_prefix: String,
+    continuation_prefix: String,
     label: &'a str,
+    depth: usize,
     is_focused: bool,
+    is_folded: bool,
+    path: Vec<usize>,
 }
 
 // Iterator type returning the strings used to display the forest.
 struct ForestIter<'a> {
     prefix_stack: Vec<IndentBlock>,
+    path: Vec<usize>,
     node_iter: Box<dyn Iterator<Item = NodeInfo<'a>> + 'a>,
 }
 
@@ -38,6 +66,7 @@ impl<'a> ForestIter<'a> {
         let node_iter = focus.into_iter().flat_map(focus_iter);
         ForestIter {
             prefix_stack: Vec::new(),
+            path: Vec::new(),
             node_iter: Box::new(node_iter),
         }
     }
@@ -52,28 +81,40 @@ impl<'a> Iterator for ForestIter<'a> {
             position,
             is_last_sibling,
             is_focused,
+            is_folded,
         } = self.node_iter.next()?;
         let mut tree_prefix = String::new();
         match position {
             NodePosition::Root => {
                 self.prefix_stack.clear();
+                match self.path.first_mut() {
+                    Some(index) => *index += 1,
+                    None => self.path.push(0),
+                }
+                self.path.truncate(1);
                 return Some(LineContent {
                     tree_prefix,
+                    continuation_prefix: String::new(),
                     label,
+                    depth: 0,
                     is_focused,
+                    is_folded,
+                    path: self.path.clone(),
                 });
             }
-            NodePosition::FirstChild => (),
+            NodePosition::FirstChild => self.path.push(0),
             NodePosition::SubsequentChild => {
                 while let Some(IndentBlock::Spacer) = self.prefix_stack.pop() {}
+                self.path.truncate(self.prefix_stack.len() + 2);
+                if let Some(index) = self.path.last_mut() {
+                    *index += 1;
+                }
             }
         }
         for block in &self.prefix_stack {
-            tree_prefix.push_str(match block {
-                IndentBlock::Spacer => "   ",
-                IndentBlock::VertBar => "│  ",
-            });
+            tree_prefix.push_str(block.as_str());
         }
+        let mut continuation_prefix = tree_prefix.clone();
         if is_last_sibling {
             tree_prefix.push_str("└──");
             self.prefix_stack.push(IndentBlock::Spacer);
@@ -81,17 +122,34 @@ impl<'a> Iterator for ForestIter<'a> {
             tree_prefix.push_str("├──");
             self.prefix_stack.push(IndentBlock::VertBar);
         }
+        if let Some(block) = self.prefix_stack.last() {
+            continuation_prefix.push_str(block.as_str());
+        }
         Some(LineContent {
             tree_prefix,
+            continuation_prefix,
             label,
+            depth: self.prefix_stack.len(),
             is_focused,
+            is_folded,
+            path: self.path.clone(),
         })
     }
 }
 
-// Content of the visible window, plus flags for if there is more to scroll.
+// A forest node formatted as one row per line of its wrapped label.
+struct NodeRows<'a> {
+    lines: Vec<Line<'a>>,
+    path: Vec<usize>,
+    is_focused: bool,
+}
+
+// Content of the visible window, the index of its first row in the whole
+// forest, plus flags for if there is more to scroll.
 struct ForestWindow<'a> {
-    items: Vec<LineContent<'a>>,
+    rows: Vec<(Line<'a>, Vec<usize>)>,
+    start: usize,
+    selected: Option<usize>,
     more_above: bool,
     more_below: bool,
 }
@@ -99,57 +157,68 @@ struct ForestWindow<'a> {
 impl ForestWindow<'_> {
     fn empty() -> Self {
         Self {
-            items: Vec::new(),
+            rows: Vec::new(),
+            start: 0,
+            selected: None,
             more_above: false,
             more_below: false,
         }
     }
 }
 
-// Build a window of visible lines centering the focused line.
-fn build_forest_window(
-    mut iter: ForestIter,
+// Build a window of visible rows containing the focused node, scrolling as
+// little as possible from the `previous` start.
+fn build_forest_window<'a>(
+    mut nodes: impl Iterator<Item = NodeRows<'a>>,
     window_height: usize,
-) -> ForestWindow {
+    previous: usize,
+) -> ForestWindow<'a> {
     if window_height == 0 {
         return ForestWindow::empty();
     }
-    let mut line_queue: VecDeque<LineContent> =
-        VecDeque::with_capacity(window_height);
-    let mut more_above = false;
-    for tree_line in iter.by_ref() {
-        if line_queue.len() == window_height {
-            line_queue.pop_front();
-            more_above = true;
+    let mut rows: VecDeque<(Line, Vec<usize>)> = VecDeque::new();
+    let mut dropped = 0;
+    let mut focus = None;
+    for node in nodes.by_ref() {
+        let top = rows.len();
+        for line in node.lines {
+            rows.push_back((line, node.path.clone()));
         }
-        if tree_line.is_focused {
-            line_queue.push_back(tree_line);
+        if node.is_focused {
+            focus = Some((top, rows.len() - top));
             break;
         }
-        line_queue.push_back(tree_line);
-    }
-    if line_queue.is_empty() {
-        return ForestWindow::empty();
+        while rows.len() > window_height {
+            rows.pop_front();
+            dropped += 1;
+        }
     }
-    let mut focus_idx = line_queue.len() - 1;
-    let center_idx = window_height / 2;
-    let mut more_below = false;
-    for tree_line in iter {
-        if line_queue.len() < window_height {
-            line_queue.push_back(tree_line);
-        } else if focus_idx <= center_idx {
-            more_below = true;
+    let (focus_top, focus_len) = focus.unwrap_or((rows.len(), 0));
+    for node in nodes.by_ref() {
+        if rows.len() >= focus_top + window_height {
             break;
-        } else {
-            line_queue.pop_front();
-            line_queue.push_back(tree_line);
-            more_above = true;
-            focus_idx -= 1;
+        }
+        for line in node.lines {
+            rows.push_back((line, node.path.clone()));
         }
     }
+    let start =
+        scroll_start(previous, dropped + focus_top, focus_len, window_height);
+    let start =
+        min(start, (dropped + rows.len()).saturating_sub(window_height));
+    let skipped = start.saturating_sub(dropped);
+    let more_below =
+        rows.len() > skipped + window_height || nodes.next().is_some();
+    let rows: Vec<_> =
+        rows.into_iter().skip(skipped).take(window_height).collect();
+    let selected = focus
+        .and_then(|(top, _)| top.checked_sub(skipped))
+        .filter(|&row| row < rows.len());
     ForestWindow {
-        items: line_queue.into_iter().collect(),
-        more_above,
+        rows,
+        start,
+        selected,
+        more_above: start > 0,
         more_below,
     }
 }
@@ -159,53 +228,224 @@ enum FocusStyle<'a> {
     Normal,
     Insert,
     Move,
-    Input(&'a str),
+    Visual(SiblingRange),
+    Input(&'a TextInput),
     Delete,
 }
 
-// Construct a styled UI Line from its content.
-fn format_line<'a>(item: LineContent<'a>, style: &FocusStyle<'a>) -> Line<'a> {
+// Split `text` into byte ranges no wider than `width`, breaking after
+// whitespace where possible. Whitespace past the width stays at the end of
+// its line.
+fn wrap_ranges(text: &str, width: usize) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    if width == 0 {
+        ranges.push(0..text.len());
+        return ranges;
+    }
+    let mut line_start = 0;
+    let mut line_width = 0;
+    let mut last_break = None;
+    for (index, grapheme) in text.grapheme_indices(true) {
+        let grapheme_width = Span::raw(grapheme).width();
+        let is_space = grapheme.chars().all(char::is_whitespace);
+        while !is_space
+            && line_width + grapheme_width > width
+            && line_start < index
+        {
+            let break_at = last_break.unwrap_or(index);
+            ranges.push(line_start..break_at);
+            line_start = break_at;
+            line_width = Span::raw(&text[line_start..index]).width();
+            last_break = None;
+        }
+        line_width += grapheme_width;
+        if is_space {
+            last_break = Some(index + grapheme.len());
+        }
+    }
+    ranges.push(line_start..text.len());
+    ranges
+}
+
+// Split styled `spans` into lines no wider than `width`.
+fn wrap_spans<'a>(spans: Vec<Span<'a>>, width: usize) -> Vec<Vec<Span<'a>>> {
+    let text: String = spans.iter().map(|span| span.content.as_ref()).collect();
+    let mut offsets = Vec::with_capacity(spans.len());
+    let mut offset = 0;
+    for span in &spans {
+        offsets.push(offset);
+        offset += span.content.len();
+    }
+    wrap_ranges(&text, width)
+        .into_iter()
+        .map(|range| {
+            spans
+                .iter()
+                .zip(&offsets)
+                .filter_map(|(span, &offset)| {
+                    let end = offset + span.content.len();
+                    let from = max(range.start, offset) - offset;
+                    let to = min(range.end, end).checked_sub(offset)?;
+                    if from >= to {
+                        return None;
+                    }
+                    let content = match &span.content {
+                        Cow::Borrowed(content) => {
+                            Cow::Borrowed(&content[from..to])
+                        }
+                        Cow::Owned(content) => {
+                            Cow::Owned(content[from..to].to_string())
+                        }
+                    };
+                    Some(Span::styled(content, span.style))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Return the number of indentation levels to elide so that the label of a
+// node at `depth` keeps at least half of `width`. The branch to the node
+// itself is never elided.
+fn elided_levels(depth: usize, width: usize) -> usize {
+    let prefix_width = INDENT.len() + depth * INDENT_BLOCK_WIDTH;
+    let excess = (prefix_width + width / 2).saturating_sub(width);
+    min(excess.div_ceil(INDENT_BLOCK_WIDTH), depth.saturating_sub(1))
+}
+
+// Remove the first `levels` indentation blocks from a tree prefix.
+fn elide_prefix(prefix: &mut String, levels: usize) {
+    let end = prefix
+        .char_indices()
+        .nth(levels * INDENT_BLOCK_WIDTH)
+        .map_or(prefix.len(), |(index, _)| index);
+    prefix.drain(..end);
+}
+
+// Construct the styled UI Lines of a node, wrapping its label to `width`
+// and highlighting it if it is in a Visual mode selection. Up to `elided`
+// levels of indentation are dropped from the left.
+fn format_lines<'a>(
+    item: LineContent<'a>,
+    is_selected: bool,
+    style: &FocusStyle<'a>,
+    width: usize,
+    elided: usize,
+) -> Vec<Line<'a>> {
     let LineContent {
-        tree_prefix,
+        mut tree_prefix,
+        mut continuation_prefix,
         label,
+        depth,
         is_focused,
+        is_folded,
+        ..
     } = item;
-    let prefix_span = Span::styled(tree_prefix, style::TEXT_TREE);
-    let (text, text_style, bg_style) = if is_focused {
-        let (text, bg_style) = match style {
-            FocusStyle::Normal => (Cow::Borrowed(label), style::BG_DEFAULT),
-            FocusStyle::Insert => (Cow::Borrowed(label), style::BG_INSERT),
-            FocusStyle::Move => (Cow::Borrowed(label), style::BG_MOVE),
+    let mut spans = Vec::new();
+    let bg_style = if is_focused {
+        let (label_spans, bg_style) = match style {
+            FocusStyle::Normal => {
+                (vec![label.into()], style::theme().bg_default)
+            }
+            FocusStyle::Insert => {
+                (vec![label.into()], style::theme().bg_insert)
+            }
+            FocusStyle::Move => (vec![label.into()], style::theme().bg_move),
+            FocusStyle::Visual(_) => {
+                (vec![label.into()], style::theme().bg_visual)
+            }
             FocusStyle::Input(input) => {
-                (Cow::Owned(format!("{input}█")), style::BG_INPUT)
+                (input_spans(input, "█".into()), style::theme().bg_input)
+            }
+            FocusStyle::Delete => {
+                (vec![label.into()], style::theme().bg_delete)
             }
-            FocusStyle::Delete => (Cow::Borrowed(label), style::BG_DELETE),
         };
-        (text, style::TEXT_SELECTED, bg_style)
+        let label_spans = label_spans
+            .into_iter()
+            .map(|span| span.patch_style(style::theme().text_selected));
+        spans.extend(label_spans);
+        bg_style
+    } else if is_selected {
+        spans.push(Span::styled(label, style::theme().text_default));
+        style::theme().bg_visual
+    } else {
+        spans.push(Span::styled(label, style::theme().text_default));
+        style::theme().bg_default
+    };
+    if is_folded {
+        spans.push(Span::styled(FOLD_HINT, style::theme().text_tree));
+    }
+    let levels = min(elided, depth.saturating_sub(1));
+    elide_prefix(&mut tree_prefix, levels);
+    elide_prefix(&mut continuation_prefix, levels);
+    let indent = if levels > 0 {
+        Span::styled(ELIDED_INDENT, style::theme().text_tree)
     } else {
-        (Cow::Borrowed(label), style::TEXT_DEFAULT, style::BG_DEFAULT)
+        Span::raw(INDENT)
     };
-    let label_span = Span::styled(text, text_style);
-    let spans = vec![Span::raw(INDENT), prefix_span, label_span];
-    Line::from(spans).style(bg_style)
+    let prefix_width = indent.width() + Span::raw(&tree_prefix).width();
+    let mut prefix = Some(tree_prefix);
+    wrap_spans(spans, width.saturating_sub(prefix_width))
+        .into_iter()
+        .map(|row_spans| {
+            let prefix =
+                prefix.take().unwrap_or_else(|| continuation_prefix.clone());
+            let mut spans = vec![
+                indent.clone(),
+                Span::styled(prefix, style::theme().text_tree),
+            ];
+            spans.extend(row_spans);
+            Line::from(spans).style(bg_style)
+        })
+        .collect()
 }
 
 // Construct a ScrollArea to display the forest, styling the focused line.
 fn new_scroll_area<'a>(
     focus: Option<&'a FocusNode>,
     style: FocusStyle<'a>,
-) -> ScrollArea<'a, impl FnOnce(usize) -> ScrollContent<'a> + 'a> {
-    let build = move |height| {
+) -> ScrollArea<'a, impl FnOnce(Viewport) -> ScrollContent<'a> + 'a> {
+    let build = move |viewport: Viewport| {
+        let Viewport {
+            width,
+            height,
+            start,
+        } = viewport;
+        let depth = focus.map_or(0, FocusNode::depth);
+        let elided = elided_levels(depth, width);
+        let nodes = ForestIter::new(focus).map(|mut item| {
+            let path = std::mem::take(&mut item.path);
+            let is_selected = matches!(
+                &style,
+                FocusStyle::Visual(range) if range.contains(&path)
+            );
+            let is_focused = item.is_focused;
+            let lines = format_lines(item, is_selected, &style, width, elided);
+            NodeRows {
+                lines,
+                path,
+                is_focused,
+            }
+        });
         let ForestWindow {
-            items,
+            rows,
+            start,
+            selected,
             more_above,
             more_below,
-        } = build_forest_window(ForestIter::new(focus), height);
-        let lines = items.into_iter().map(|item| format_line(item, &style));
+        } = build_forest_window(nodes, height, start);
+        let (lines, targets): (Vec<_>, Vec<_>) = rows
+            .into_iter()
+            .map(|(line, path)| (line, ClickTarget::Node(path)))
+            .unzip();
         ScrollContent {
-            text: Text::from_iter(lines),
+            text: Text::from(lines),
+            start,
             more_above,
             more_below,
+            targets,
+            selected,
         }
     };
     ScrollArea { build }
@@ -214,35 +454,117 @@ fn new_scroll_area<'a>(
 /// Return a ScrollArea widget for Normal mode.
 pub fn normal<'a>(
     focus: Option<&'a FocusNode>,
-) -> ScrollArea<'a, impl FnOnce(usize) -> ScrollContent<'a> + 'a> {
+) -> ScrollArea<'a, impl FnOnce(Viewport) -> ScrollContent<'a> + 'a> {
     new_scroll_area(focus, FocusStyle::Normal)
 }
 
 /// Return a ScrollArea widget for selecting an insert position.
 pub fn insert<'a>(
     focus: Option<&'a FocusNode>,
-) -> ScrollArea<'a, impl FnOnce(usize) -> ScrollContent<'a> + 'a> {
+) -> ScrollArea<'a, impl FnOnce(Viewport) -> ScrollContent<'a> + 'a> {
     new_scroll_area(focus, FocusStyle::Insert)
 }
 
 /// Return a ScrollArea widget for Move mode.
 pub fn move_mode<'a>(
     focus: Option<&'a FocusNode>,
-) -> ScrollArea<'a, impl FnOnce(usize) -> ScrollContent<'a> + 'a> {
+) -> ScrollArea<'a, impl FnOnce(Viewport) -> ScrollContent<'a> + 'a> {
     new_scroll_area(focus, FocusStyle::Move)
 }
 
+/// Return a ScrollArea widget highlighting the siblings in a Visual mode
+/// `range`.
+pub fn visual<'a>(
+    focus: Option<&'a FocusNode>,
+    range: SiblingRange,
+) -> ScrollArea<'a, impl FnOnce(Viewport) -> ScrollContent<'a> + 'a> {
+    new_scroll_area(focus, FocusStyle::Visual(range))
+}
+
 /// Return a ScrollArea widget with user `input` on the focused line.
 pub fn input<'a>(
     focus: Option<&'a FocusNode>,
-    input: &'a str,
-) -> ScrollArea<'a, impl FnOnce(usize) -> ScrollContent<'a> + 'a> {
+    input: &'a TextInput,
+) -> ScrollArea<'a, impl FnOnce(Viewport) -> ScrollContent<'a> + 'a> {
     new_scroll_area(focus, FocusStyle::Input(input))
 }
 
 /// Return a ScrollArea widget for confirming a deletion.
 pub fn delete<'a>(
     focus: Option<&'a FocusNode>,
-) -> ScrollArea<'a, impl FnOnce(usize) -> ScrollContent<'a> + 'a> {
+) -> ScrollArea<'a, impl FnOnce(Viewport) -> ScrollContent<'a> + 'a> {
     new_scroll_area(focus, FocusStyle::Delete)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wrap(text: &str, width: usize) -> Vec<&str> {
+        wrap_ranges(text, width)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect()
+    }
+
+    #[test]
+    fn wrap_fitting() {
+        assert_eq!(wrap("abc", 5), ["abc"]);
+        assert_eq!(wrap("abcde", 5), ["abcde"]);
+        assert_eq!(wrap("", 5), [""]);
+        assert_eq!(wrap("a b", 0), ["a b"]);
+    }
+
+    #[test]
+    fn wrap_after_whitespace() {
+        assert_eq!(wrap("hello world", 6), ["hello ", "world"]);
+        assert_eq!(wrap("hello world", 5), ["hello ", "world"]);
+        assert_eq!(wrap("a b c d", 3), ["a b ", "c d"]);
+        assert_eq!(wrap("a   b", 2), ["a   ", "b"]);
+    }
+
+    #[test]
+    fn wrap_long_words() {
+        assert_eq!(wrap("abcdefgh", 3), ["abc", "def", "gh"]);
+        assert_eq!(wrap("a bcdefg", 3), ["a ", "bcd", "efg"]);
+        assert_eq!(wrap("abcdef", 1), ["a", "b", "c", "d", "e", "f"]);
+    }
+
+    #[test]
+    fn wrap_wide_graphemes() {
+        assert_eq!(wrap("日本語", 4), ["日本", "語"]);
+        assert_eq!(wrap("日本語", 1), ["日", "本", "語"]);
+        assert_eq!(wrap("e\u{301}e\u{301}", 1), ["e\u{301}", "e\u{301}"]);
+    }
+
+    #[test]
+    fn elide_nothing_when_wide() {
+        assert_eq!(elided_levels(0, 0), 0);
+        assert_eq!(elided_levels(3, 80), 0);
+        // The prefix of 2 + 6 * 3 columns takes exactly half of the width.
+        assert_eq!(elided_levels(6, 40), 0);
+    }
+
+    #[test]
+    fn elide_to_half_width() {
+        assert_eq!(elided_levels(7, 40), 1);
+        // 32 columns of prefix must shrink to 20.
+        assert_eq!(elided_levels(10, 40), 4);
+    }
+
+    #[test]
+    fn elide_keeps_last_branch() {
+        assert_eq!(elided_levels(1, 2), 0);
+        assert_eq!(elided_levels(10, 10), 9);
+        assert_eq!(elided_levels(10, 0), 9);
+    }
+
+    #[test]
+    fn elide_prefix_blocks() {
+        let mut prefix = String::from("│  │     └──");
+        elide_prefix(&mut prefix, 1);
+        assert_eq!(prefix, "│     └──");
+        elide_prefix(&mut prefix, 5);
+        assert_eq!(prefix, "");
+    }
+}
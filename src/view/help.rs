@@ -0,0 +1,99 @@
+use std::cmp::min;
+
+use ratatui::{
+    prelude::{Buffer, Rect, StatefulWidget, Widget},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear},
+};
+
+use crate::{
+    model::help::HelpState,
+    view::{
+        ClickMap, INDENT,
+        scroll::{ScrollArea, ScrollContent, Viewport},
+        style,
+    },
+};
+
+// Widest the overlay grows, and the margin kept around it.
+const MAX_WIDTH: u16 = 76;
+const MARGIN: u16 = 2;
+
+/// Popup listing the keys of a mode over the main area.
+pub struct HelpPopup<'a> {
+    help_state: &'a HelpState,
+}
+
+/// Return the help overlay widget for the `help_state`.
+pub fn help(help_state: &HelpState) -> HelpPopup<'_> {
+    HelpPopup { help_state }
+}
+
+// Return a ScrollArea showing the help entries from the scroll offset.
+fn entries<'a>(
+    help_state: &'a HelpState,
+) -> ScrollArea<'a, impl FnOnce(Viewport) -> ScrollContent<'a> + 'a> {
+    let build = move |Viewport { height, .. }| {
+        let entries = &help_state.entries;
+        let start =
+            min(help_state.scroll, entries.len().saturating_sub(height));
+        let end = min(start + height, entries.len());
+        let key_width = entries
+            .iter()
+            .map(|entry| entry.keys.chars().count())
+            .max()
+            .unwrap_or(0);
+        let description_width = entries
+            .iter()
+            .map(|entry| entry.description.chars().count())
+            .max()
+            .unwrap_or(0);
+        let lines = entries[start..end].iter().map(|entry| {
+            Line::from(vec![
+                Span::raw(INDENT),
+                Span::styled(
+                    format!("{:key_width$}  ", entry.keys),
+                    style::theme().text_selected,
+                ),
+                Span::styled(
+                    format!("{:description_width$}  ", entry.description),
+                    style::theme().text_default,
+                ),
+                Span::styled(entry.name, style::theme().text_tree),
+            ])
+        });
+        ScrollContent {
+            text: Text::from_iter(lines),
+            start,
+            more_above: start > 0,
+            more_below: end < entries.len(),
+            targets: Vec::new(),
+            selected: None,
+        }
+    };
+    ScrollArea { build }
+}
+
+impl StatefulWidget for HelpPopup<'_> {
+    type State = ClickMap;
+
+    fn render(self, area: Rect, buf: &mut Buffer, click_map: &mut ClickMap) {
+        let width = min(area.width.saturating_sub(2 * MARGIN), MAX_WIDTH);
+        let popup = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + min(MARGIN / 2, area.height),
+            width,
+            height: area.height.saturating_sub(MARGIN),
+        };
+        let title = format!(" {} keys ", self.help_state.title);
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .title(title)
+            .border_style(style::theme().text_tree)
+            .style(style::theme().bg_default);
+        let inner = block.inner(popup);
+        Clear.render(popup, buf);
+        block.render(popup, buf);
+        StatefulWidget::render(entries(self.help_state), inner, buf, click_map);
+    }
+}
@@ -1,20 +1,36 @@
+use std::cmp::{max, min};
+
 use ratatui::{
-    prelude::{Buffer, Rect, Widget},
+    prelude::{Buffer, Rect, StatefulWidget, Widget},
     text::Text,
     widgets::Block,
 };
 
-use crate::view::{SCROLL_HINT, style, top_mid_bottom};
+use crate::{
+    config,
+    view::{ClickMap, ClickTarget, SCROLL_HINT, style, top_mid_bottom},
+};
+
+/// The size of a scrolled area, and the index of the first row it showed in
+/// the previous frame.
+pub struct Viewport {
+    pub width: usize,
+    pub height: usize,
+    pub start: usize,
+}
 
 pub struct ScrollContent<'a> {
     pub text: Text<'a>,
+    pub start: usize,
     pub more_above: bool,
     pub more_below: bool,
+    pub targets: Vec<ClickTarget>,
+    pub selected: Option<usize>,
 }
 
 pub struct ScrollArea<'a, F>
 where
-    F: FnOnce(usize) -> ScrollContent<'a>,
+    F: FnOnce(Viewport) -> ScrollContent<'a>,
 {
     pub build: F,
 }
@@ -23,24 +39,125 @@ fn scroll_hint(more: bool) -> &'static str {
     if more { SCROLL_HINT } else { "" }
 }
 
-impl<'a, F> Widget for ScrollArea<'a, F>
+/// Return the first row to show in a `height` window so that the `len` rows
+/// of the selection from `top` keep the configured scrolloff margin, moving
+/// as little as possible from the `previous` start.
+pub fn scroll_start(
+    previous: usize,
+    top: usize,
+    len: usize,
+    height: usize,
+) -> usize {
+    let scrolloff = config::get().view.scrolloff;
+    start_with_margin(previous, top, len, height, scrolloff)
+}
+
+// Return the first row to show as in `scroll_start`, with a `scrolloff`
+// margin that shrinks to fit the `height`.
+fn start_with_margin(
+    previous: usize,
+    top: usize,
+    len: usize,
+    height: usize,
+    scrolloff: usize,
+) -> usize {
+    let margin = min(scrolloff, height.saturating_sub(1) / 2);
+    let lowest = (top + len + margin).saturating_sub(height);
+    let highest = top.saturating_sub(margin);
+    min(max(previous, lowest), highest)
+}
+
+impl<'a, F> StatefulWidget for ScrollArea<'a, F>
 where
-    F: FnOnce(usize) -> ScrollContent<'a>,
+    F: FnOnce(Viewport) -> ScrollContent<'a>,
 {
-    fn render(self, area: Rect, buf: &mut Buffer) {
+    type State = ClickMap;
+
+    fn render(self, area: Rect, buf: &mut Buffer, click_map: &mut ClickMap) {
         let [top_line, mid_area, bottom_line] = top_mid_bottom(area);
         let ScrollContent {
             text,
+            start,
             more_above,
             more_below,
-        } = (self.build)(mid_area.height as usize);
-        Block::new().style(style::BG_DEFAULT).render(mid_area, buf);
+            targets,
+            selected,
+        } = (self.build)(Viewport {
+            width: mid_area.width as usize,
+            height: mid_area.height as usize,
+            start: click_map.start,
+        });
+        Block::new()
+            .style(style::theme().bg_default)
+            .render(mid_area, buf);
         text.render(mid_area, buf);
         Text::from(scroll_hint(more_above))
-            .style(style::DEFAULT)
+            .style(style::theme().default)
             .render(top_line, buf);
         Text::from(scroll_hint(more_below))
-            .style(style::DEFAULT)
+            .style(style::theme().default)
             .render(bottom_line, buf);
+        *click_map = ClickMap {
+            top: mid_area.y,
+            height: mid_area.height as usize,
+            start,
+            targets,
+            selected,
+        };
+    }
+}
+
+impl<'a, F> Widget for ScrollArea<'a, F>
+where
+    F: FnOnce(Viewport) -> ScrollContent<'a>,
+{
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        StatefulWidget::render(self, area, buf, &mut ClickMap::default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The start for a `height` of 20 with a scrolloff of 5.
+    fn start(previous: usize, top: usize, len: usize) -> usize {
+        start_with_margin(previous, top, len, 20, 5)
+    }
+
+    #[test]
+    fn keeps_previous_start() {
+        assert_eq!(start(0, 3, 1), 0);
+        assert_eq!(start(10, 20, 1), 10);
+        assert_eq!(start(10, 15, 10), 10);
+    }
+
+    #[test]
+    fn scrolls_to_margin() {
+        assert_eq!(start(0, 15, 1), 1);
+        assert_eq!(start(0, 100, 1), 86);
+        assert_eq!(start(50, 52, 1), 47);
+        assert_eq!(start(50, 2, 1), 0);
+    }
+
+    #[test]
+    fn tall_selection_shows_top() {
+        assert_eq!(start(0, 10, 30), 5);
+        assert_eq!(start(40, 10, 30), 5);
+    }
+
+    #[test]
+    fn margin_fits_height() {
+        assert_eq!(start_with_margin(0, 3, 1, 4, 5), 1);
+        assert_eq!(start_with_margin(0, 5, 1, 1, 5), 5);
+        assert_eq!(start_with_margin(9, 5, 1, 1, 5), 5);
+        assert_eq!(start_with_margin(0, 5, 1, 0, 5), 5);
+    }
+
+    #[test]
+    fn no_margin() {
+        assert_eq!(start_with_margin(0, 19, 1, 20, 0), 0);
+        assert_eq!(start_with_margin(0, 20, 1, 20, 0), 1);
+        assert_eq!(start_with_margin(5, 5, 1, 20, 0), 5);
     }
 }
@@ -2,10 +2,14 @@ use ratatui::{
     style::{Styled, Stylize},
     text::{Line, Span},
 };
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::{
-    model::{ConfirmState, FilenameAction, FilenameStatus, LabelAction, Model},
-    view::{INDENT, style},
+    model::{
+        CommandLineState, ConfirmState, FilenameAction, FilenameStatus,
+        LabelAction, MarkAction, Model, SessionState, text_input::TextInput,
+    },
+    view::{INDENT, input_spans, style},
 };
 
 mod confirm {
@@ -18,6 +22,7 @@ mod input {
     pub const INSERT: &str = "Insert item";
     pub const RENAME_FILE: &str = "Rename file";
     pub const SAVENEW: &str = "Save new file";
+    pub const SEARCH: &str = "Search";
 }
 mod alert {
     pub const EMPTY: &str = "Empty";
@@ -26,9 +31,21 @@ mod alert {
 }
 const LOAD: &str = "Open a file or start a new session";
 const MOVE: &str = "Move subtree";
+const VISUAL: &str = "Select siblings";
+const HELP: &str = "Help";
 const INSERT: &str = "Enter position to insert new item";
 const SAVE: &str = "Save changes?";
+const SET_MARK: &str = "Set mark";
+const JUMP_MARK: &str = "Jump to mark";
+const PASTE: &str = "Insert pasted lines as";
 const UNTITLED: &str = "Untitled";
+const READ_ONLY: &str = "Read Only";
+const RECORDING: &str = "Recording";
+const CHANGE: &str = "change";
+const HOISTED: &str = "Hoisted";
+const DEPTH: &str = "depth";
+const BREADCRUMB_SEPARATOR: &str = " › ";
+const ELLIPSIS: &str = "…";
 
 fn info(text: &str) -> Span<'_> {
     format!("[{text}]").into()
@@ -50,22 +67,161 @@ fn status_info<'a>(
     }
 }
 
-// Normal mode status bar Line with the filename, if it exists.
-fn status_normal(maybe_filename: Option<&str>) -> Vec<Span<'_>> {
-    vec![match maybe_filename {
+// Return the `labels` joined as a breadcrumb, dropping labels and then
+// characters from the left to fit in `width` columns.
+fn breadcrumb(labels: &[&str], width: usize) -> String {
+    let fits = |text: &str| Span::raw(text).width() <= width;
+    let full = labels.join(BREADCRUMB_SEPARATOR);
+    if fits(&full) {
+        return full;
+    }
+    for start in 1..labels.len() {
+        let rest = labels[start..].join(BREADCRUMB_SEPARATOR);
+        let text = format!("{ELLIPSIS}{BREADCRUMB_SEPARATOR}{rest}");
+        if fits(&text) {
+            return text;
+        }
+    }
+    let last = labels.last().copied().unwrap_or_default();
+    let mut used = Span::raw(ELLIPSIS).width();
+    let mut kept = Vec::new();
+    for grapheme in last.graphemes(true).rev() {
+        used += Span::raw(grapheme).width();
+        if used > width {
+            break;
+        }
+        kept.push(grapheme);
+    }
+    kept.reverse();
+    format!("{ELLIPSIS}{}", kept.concat())
+}
+
+// Normal mode status bar Line with the filename, the breadcrumb and position
+// of the focused node and the search pattern, fitting the breadcrumb in
+// `width` columns.
+fn status_normal(state: &SessionState, width: u16) -> Vec<Span<'_>> {
+    let mut spans = vec![match state.get_filename() {
         Some(filename) => filename.bold(),
+        None if state.read_only => info(READ_ONLY),
         None => info(UNTITLED),
-    }]
+    }];
+    if state.hoist.is_some() {
+        spans.extend([" | ".into(), info(HOISTED)]);
+    }
+    let forest = state.whole_forest();
+    let labels = forest
+        .focus
+        .as_ref()
+        .map(|focus| focus.ancestor_labels())
+        .unwrap_or_default();
+    let mut rest = Vec::new();
+    if let Some(focus) = &forest.focus {
+        let index = focus.sibling_index();
+        let count = index + 1 + focus.count_next();
+        let depth = focus.depth() + 1;
+        rest.extend([
+            " | ".into(),
+            format!("{}/{count}", index + 1).into(),
+            " | ".into(),
+            format!("{DEPTH} {depth}").into(),
+        ]);
+    }
+    rest.extend(status_rest(state));
+    if !labels.is_empty() {
+        let used: usize = [INDENT.into(), " | ".into()]
+            .iter()
+            .chain(&spans)
+            .chain(&rest)
+            .map(Span::width)
+            .sum();
+        let available = usize::from(width).saturating_sub(used);
+        if available > 0 {
+            let text = breadcrumb(&labels, available);
+            spans.extend([" | ".into(), text.into()]);
+        }
+    }
+    spans.extend(rest);
+    spans
+}
+
+// Normal mode status bar spans following the position of the focused node.
+fn status_rest(state: &SessionState) -> Vec<Span<'_>> {
+    let mut spans = Vec::new();
+    if let Some(pattern) = &state.search {
+        spans.extend([" | ".into(), format!("/{pattern}").into()]);
+    }
+    spans.extend(undo_info(state));
+    spans.extend(count_info(state.count));
+    if let Some(register) = state.recording {
+        let recording = format!("[{RECORDING} @{register}]");
+        spans.extend([" | ".into(), recording.into()]);
+    }
+    if let Some(alert) = &state.alert {
+        spans.extend([" | ".into(), info(alert)]);
+    }
+    spans
 }
 
-/// Return the status bar widget based on the `model`.
-pub fn status_bar(model: &Model) -> Line<'_> {
+// Spans showing the undo position among the changes made, if there are any.
+fn undo_info(state: &SessionState) -> Vec<Span<'static>> {
+    let history = &state.history;
+    match history.len() {
+        1 => Vec::new(),
+        len => {
+            let position =
+                format!("[{CHANGE} {}/{}]", history.position(), len - 1);
+            vec![" | ".into(), position.into()]
+        }
+    }
+}
+
+// Spans showing the pending count, if present.
+fn count_info(maybe_count: Option<usize>) -> Vec<Span<'static>> {
+    match maybe_count {
+        Some(count) => vec![" | ".into(), count.to_string().bold()],
+        None => Vec::new(),
+    }
+}
+
+// Search mode status bar Line with the user input.
+fn status_search(input: &TextInput) -> Vec<Span<'_>> {
+    let mut spans = vec![input::SEARCH.into(), " /".into()];
+    spans.extend(input_spans(input, "█".slow_blink()));
+    spans
+}
+
+// Command line mode status bar Line with the user input and completions.
+fn status_command_line(state: &CommandLineState) -> Vec<Span<'_>> {
+    let mut spans = vec![":".into()];
+    spans.extend(input_spans(&state.input, "█".slow_blink()));
+    if !state.completions.is_empty() {
+        spans.extend([" | ".into(), state.completions.join(" ").into()]);
+    }
+    spans
+}
+
+/// Return the status bar widget based on the `model`, fitting `width`
+/// columns.
+pub fn status_bar(model: &Model, width: u16) -> Line<'_> {
     let content = match model {
         Model::Load(_) => status(LOAD),
-        Model::Normal(state) => status_normal(state.get_filename()),
+        Model::Normal(state) => status_normal(state, width),
         Model::Insert(_) => status(INSERT),
-        Model::Move(_) => status(MOVE),
+        Model::Move(state) => {
+            let mut spans = status(MOVE);
+            spans.extend(undo_info(state));
+            spans.extend(count_info(state.count));
+            spans
+        }
+        Model::Visual(visual_state) => {
+            let selected = format!("[{} selected]", visual_state.range().len());
+            let mut spans = vec![VISUAL.into(), " | ".into(), selected.into()];
+            spans.extend(count_info(visual_state.session.count));
+            spans
+        }
+        Model::Help(help_state) => status_info(HELP, Some(help_state.title)),
         Model::Save(_) => status(SAVE),
+        Model::Paste(_) => status(PASTE),
         Model::LabelInput(label_state) => {
             let message = match label_state.action {
                 LabelAction::Insert => input::INSERT,
@@ -91,6 +247,14 @@ pub fn status_bar(model: &Model) -> Line<'_> {
             };
             status_info(message, info)
         }
+        Model::Search(search_state) => status_search(&search_state.input),
+        Model::CommandLine(command_line_state) => {
+            status_command_line(command_line_state)
+        }
+        Model::Mark(mark_state) => match mark_state.action {
+            MarkAction::Set => status(SET_MARK),
+            MarkAction::Jump => status(JUMP_MARK),
+        },
         Model::Confirm(confirm_state) => match confirm_state {
             ConfirmState::NewSession => status(confirm::NEW),
             ConfirmState::DeleteItem(_) => status(confirm::DELETE_ITEM),
@@ -99,5 +263,7 @@ pub fn status_bar(model: &Model) -> Line<'_> {
     };
     let mut spans = vec![Span::raw(INDENT)];
     spans.extend(content);
-    Line::from(spans).left_aligned().set_style(style::ACCENT)
+    Line::from(spans)
+        .left_aligned()
+        .set_style(style::theme().accent)
 }
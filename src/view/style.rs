@@ -1,4 +1,47 @@
+use std::collections::HashMap;
+
+use once_cell::sync::OnceCell;
 use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+use crate::{config, io::fs};
+
+/// The styles of each semantic part of the UI.
+pub struct Theme {
+    pub text_tree: Style,
+    pub text_default: Style,
+    pub text_selected: Style,
+    pub bg_default: Style,
+    pub bg_insert: Style,
+    pub bg_move: Style,
+    pub bg_visual: Style,
+    pub bg_input: Style,
+    pub bg_delete: Style,
+    pub default: Style,
+    pub default_hl: Style,
+    pub delete: Style,
+    pub accent: Style,
+    pub cursor: Style,
+    pub cmd_key: Style,
+    pub cmd_name: Style,
+}
+
+// The colours of one style in a theme file, as names or `#rrggbb`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColorSpec {
+    fg: Option<String>,
+    bg: Option<String>,
+}
+
+// A user theme: the built-in theme it starts from, and the colours of the
+// styles it changes.
+#[derive(Deserialize)]
+struct ThemeFile {
+    base: Option<String>,
+    #[serde(flatten)]
+    styles: HashMap<String, ColorSpec>,
+}
 
 const WARM_GRAY: Color = Color::Rgb(64, 58, 55);
 const LIGHT_WARM_GRAY: Color = Color::Rgb(89, 81, 71);
@@ -10,27 +53,200 @@ const DARK_IVORY: Color = Color::Rgb(248, 232, 180);
 const RED: Color = Color::Rgb(171, 26, 10);
 const AMBER: Color = Color::Rgb(160, 110, 30);
 const GREEN: Color = Color::Rgb(130, 150, 70);
+const SLATE: Color = Color::Rgb(70, 90, 120);
+
+const PAPER: Color = Color::Rgb(250, 246, 236);
+const DARK_PAPER: Color = Color::Rgb(236, 229, 212);
+const SAND: Color = Color::Rgb(222, 212, 190);
+const INK: Color = Color::Rgb(48, 44, 41);
+const DARK_INK: Color = Color::Rgb(20, 18, 17);
+const PALE_GRAY: Color = Color::Rgb(140, 136, 130);
+const PALE_RED: Color = Color::Rgb(240, 165, 150);
+const PALE_AMBER: Color = Color::Rgb(240, 205, 140);
+const PALE_GREEN: Color = Color::Rgb(190, 215, 150);
+const PALE_SLATE: Color = Color::Rgb(180, 200, 230);
+
+const BLACK: Color = Color::Rgb(0, 0, 0);
+const WHITE: Color = Color::Rgb(255, 255, 255);
+const CHARCOAL: Color = Color::Rgb(40, 40, 40);
+const SILVER: Color = Color::Rgb(190, 190, 190);
+const YELLOW: Color = Color::Rgb(255, 220, 0);
+const DEEP_RED: Color = Color::Rgb(170, 0, 0);
+const DEEP_AMBER: Color = Color::Rgb(140, 70, 0);
+const DEEP_GREEN: Color = Color::Rgb(0, 100, 0);
+const DEEP_BLUE: Color = Color::Rgb(0, 0, 160);
+
+// Warm grays for dark terminals.
+const DARK: Theme = Theme {
+    text_tree: Style::new().fg(COOL_GRAY),
+    text_default: Style::new().fg(IVORY),
+    text_selected: Style::new().fg(Color::White).add_modifier(Modifier::BOLD),
+    bg_default: Style::new().bg(WARM_GRAY),
+    bg_insert: Style::new().bg(GREEN),
+    bg_move: Style::new().bg(AMBER),
+    bg_visual: Style::new().bg(SLATE),
+    bg_input: Style::new().bg(DARK_WARM_GRAY),
+    bg_delete: Style::new().bg(RED),
+    default: Style::new().fg(IVORY).bg(WARM_GRAY),
+    default_hl: Style::new().fg(DARKER_WARM_GRAY).bg(IVORY),
+    delete: Style::new()
+        .fg(Color::White)
+        .bg(RED)
+        .add_modifier(Modifier::BOLD),
+    accent: Style::new().fg(IVORY).bg(LIGHT_WARM_GRAY),
+    cursor: Style::new()
+        .fg(IVORY)
+        .bg(WARM_GRAY)
+        .add_modifier(Modifier::SLOW_BLINK),
+    cmd_key: Style::new()
+        .fg(DARKER_WARM_GRAY)
+        .bg(DARK_IVORY)
+        .add_modifier(Modifier::BOLD),
+    cmd_name: Style::new()
+        .fg(IVORY)
+        .bg(LIGHT_WARM_GRAY)
+        .add_modifier(Modifier::ITALIC),
+};
+
+// Dark ink on paper for light terminals.
+const LIGHT: Theme = Theme {
+    text_tree: Style::new().fg(PALE_GRAY),
+    text_default: Style::new().fg(INK),
+    text_selected: Style::new().fg(DARK_INK).add_modifier(Modifier::BOLD),
+    bg_default: Style::new().bg(PAPER),
+    bg_insert: Style::new().bg(PALE_GREEN),
+    bg_move: Style::new().bg(PALE_AMBER),
+    bg_visual: Style::new().bg(PALE_SLATE),
+    bg_input: Style::new().bg(DARK_PAPER),
+    bg_delete: Style::new().bg(PALE_RED),
+    default: Style::new().fg(INK).bg(PAPER),
+    default_hl: Style::new().fg(PAPER).bg(INK),
+    delete: Style::new().fg(WHITE).bg(RED).add_modifier(Modifier::BOLD),
+    accent: Style::new().fg(INK).bg(SAND),
+    cursor: Style::new()
+        .fg(INK)
+        .bg(PAPER)
+        .add_modifier(Modifier::SLOW_BLINK),
+    cmd_key: Style::new()
+        .fg(PAPER)
+        .bg(LIGHT_WARM_GRAY)
+        .add_modifier(Modifier::BOLD),
+    cmd_name: Style::new().fg(INK).bg(SAND).add_modifier(Modifier::ITALIC),
+};
+
+// White on black with saturated highlights.
+const HIGH_CONTRAST: Theme = Theme {
+    text_tree: Style::new().fg(SILVER),
+    text_default: Style::new().fg(WHITE),
+    text_selected: Style::new().fg(WHITE).add_modifier(Modifier::BOLD),
+    bg_default: Style::new().bg(BLACK),
+    bg_insert: Style::new().bg(DEEP_GREEN),
+    bg_move: Style::new().bg(DEEP_AMBER),
+    bg_visual: Style::new().bg(DEEP_BLUE),
+    bg_input: Style::new().bg(CHARCOAL),
+    bg_delete: Style::new().bg(DEEP_RED),
+    default: Style::new().fg(WHITE).bg(BLACK),
+    default_hl: Style::new().fg(BLACK).bg(WHITE),
+    delete: Style::new()
+        .fg(WHITE)
+        .bg(DEEP_RED)
+        .add_modifier(Modifier::BOLD),
+    accent: Style::new().fg(WHITE).bg(CHARCOAL),
+    cursor: Style::new()
+        .fg(WHITE)
+        .bg(BLACK)
+        .add_modifier(Modifier::SLOW_BLINK),
+    cmd_key: Style::new()
+        .fg(BLACK)
+        .bg(YELLOW)
+        .add_modifier(Modifier::BOLD),
+    cmd_name: Style::new()
+        .fg(WHITE)
+        .bg(CHARCOAL)
+        .add_modifier(Modifier::ITALIC),
+};
+
+static THEME: OnceCell<Theme> = OnceCell::new();
+
+impl Theme {
+    // Return the built-in theme called `name`, if any.
+    fn built_in(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(DARK),
+            "light" => Some(LIGHT),
+            "high-contrast" => Some(HIGH_CONTRAST),
+            _ => None,
+        }
+    }
+
+    // Return the style called `name` in a theme file, if any.
+    fn style_mut(&mut self, name: &str) -> Option<&mut Style> {
+        let style = match name {
+            "text_tree" => &mut self.text_tree,
+            "text_default" => &mut self.text_default,
+            "text_selected" => &mut self.text_selected,
+            "bg_default" => &mut self.bg_default,
+            "bg_insert" => &mut self.bg_insert,
+            "bg_move" => &mut self.bg_move,
+            "bg_visual" => &mut self.bg_visual,
+            "bg_input" => &mut self.bg_input,
+            "bg_delete" => &mut self.bg_delete,
+            "default" => &mut self.default,
+            "default_hl" => &mut self.default_hl,
+            "delete" => &mut self.delete,
+            "accent" => &mut self.accent,
+            "cursor" => &mut self.cursor,
+            "cmd_key" => &mut self.cmd_key,
+            "cmd_name" => &mut self.cmd_name,
+            _ => return None,
+        };
+        Some(style)
+    }
+}
+
+// Parse a colour of the `key` style in a theme `file`.
+fn parse_color(text: &str, key: &str, file: &str) -> Result<Color, String> {
+    text.parse()
+        .map_err(|_| format!("{file}: Invalid colour `{text}` for `{key}`"))
+}
+
+// Return the built-in theme or the theme file called `name`.
+fn load(name: &str) -> Result<Theme, String> {
+    if let Some(theme) = Theme::built_in(name) {
+        return Ok(theme);
+    }
+    let text = fs::read_theme(name)
+        .ok_or_else(|| format!("Unknown theme `{name}`"))?;
+    let file_name = format!("themes/{name}.toml");
+    let file: ThemeFile = config::parse_toml(&text, &file_name)?;
+    let base = file.base.as_deref().unwrap_or("dark");
+    let mut theme = Theme::built_in(base)
+        .ok_or_else(|| format!("{file_name}: Unknown base theme `{base}`"))?;
+    for (key, spec) in file.styles {
+        let style = theme
+            .style_mut(&key)
+            .ok_or_else(|| format!("{file_name}: Unknown style `{key}`"))?;
+        if let Some(fg) = spec.fg {
+            *style = style.fg(parse_color(&fg, &key, &file_name)?);
+        }
+        if let Some(bg) = spec.bg {
+            *style = style.bg(parse_color(&bg, &key, &file_name)?);
+        }
+    }
+    Ok(theme)
+}
+
+/// Load the theme called `name`, or the one in the config if None, or return
+/// an error if it is invalid. Must be called before anything is drawn.
+pub fn init(name: Option<&str>) -> Result<(), String> {
+    let name = name.unwrap_or(&config::get().view.theme);
+    if THEME.set(load(name)?).is_err() {
+        panic!("Theme already loaded");
+    }
+    Ok(())
+}
 
-pub const TEXT_TREE: Style = Style::new().fg(COOL_GRAY);
-pub const TEXT_DEFAULT: Style = Style::new().fg(IVORY);
-pub const TEXT_SELECTED: Style =
-    Style::new().fg(Color::White).add_modifier(Modifier::BOLD);
-pub const BG_DEFAULT: Style = Style::new().bg(WARM_GRAY);
-pub const BG_INSERT: Style = Style::new().bg(GREEN);
-pub const BG_MOVE: Style = Style::new().bg(AMBER);
-pub const BG_INPUT: Style = Style::new().bg(DARK_WARM_GRAY);
-pub const BG_DELETE: Style = Style::new().bg(RED);
-
-pub const DEFAULT: Style = Style::new().fg(IVORY).bg(WARM_GRAY);
-pub const DEFAULT_HL: Style = Style::new().fg(DARKER_WARM_GRAY).bg(IVORY);
-pub const DELETE: Style = Style::new()
-    .fg(Color::White)
-    .bg(RED)
-    .add_modifier(Modifier::BOLD);
-pub const ACCENT: Style = Style::new().fg(IVORY).bg(LIGHT_WARM_GRAY);
-pub const CURSOR: Style = DEFAULT.add_modifier(Modifier::SLOW_BLINK);
-pub const CMD_KEY: Style = Style::new()
-    .fg(DARKER_WARM_GRAY)
-    .bg(DARK_IVORY)
-    .add_modifier(Modifier::BOLD);
-pub const CMD_NAME: Style = ACCENT.add_modifier(Modifier::ITALIC);
+/// Return the current theme.
+pub fn theme() -> &'static Theme {
+    THEME.get().expect("Theme not loaded")
+}
@@ -1,8 +1,17 @@
 pub mod iter;
+pub mod legacy;
+pub mod pool;
 
-use std::rc::Rc;
+use std::{
+    ops::ControlFlow,
+    rc::Rc,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+
+// Identifier for the next new node, above every identifier read so far.
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
 
 // A node in a multi-way forest stored using child-sibling representation.
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -10,6 +19,10 @@ struct Node {
     child: Option<Rc<Node>>,
     next: Option<Rc<Node>>,
     label: Rc<str>,
+    #[serde(deserialize_with = "read_id")]
+    id: u64,
+    #[serde(skip)]
+    folded: bool,
 }
 
 // A node with a reversed sibling chain for leftward traversal.
@@ -18,6 +31,10 @@ struct RevNode {
     child: Option<Rc<Node>>,
     prev: Option<Rc<RevNode>>,
     label: Rc<str>,
+    #[serde(deserialize_with = "read_id")]
+    id: u64,
+    #[serde(skip)]
+    folded: bool,
 }
 
 // A node in the path from the focused node up to the root of its tree.
@@ -27,6 +44,25 @@ struct PathNode {
     prev: Option<Rc<RevNode>>,
     next: Option<Rc<Node>>,
     label: Rc<str>,
+    #[serde(deserialize_with = "read_id")]
+    id: u64,
+}
+
+/// The ancestors and siblings of a hoisted node, set aside while its subtree
+/// is focused as a forest of its own.
+#[derive(Clone)]
+pub struct Hoist {
+    parent: Option<Rc<PathNode>>,
+    prev: Option<Rc<RevNode>>,
+    next: Option<Rc<Node>>,
+    id: u64,
+}
+
+/// An owned tree used to build a forest from external data.
+#[derive(Clone)]
+pub struct Tree {
+    pub label: String,
+    pub children: Vec<Tree>,
 }
 
 /// The focused node in a zipper for a multi-way forest.
@@ -37,6 +73,29 @@ pub struct FocusNode {
     prev: Option<Rc<RevNode>>,
     next: Option<Rc<Node>>,
     label: Rc<str>,
+    #[serde(deserialize_with = "read_id")]
+    id: u64,
+    #[serde(skip)]
+    folded: bool,
+}
+
+// Allocate a new node identifier.
+fn new_id() -> u64 {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+// Reserve a node identifier read from a file so new nodes never reuse it.
+fn reserve_id(id: u64) {
+    NEXT_ID.fetch_max(id + 1, Ordering::Relaxed);
+}
+
+// Deserialize a node identifier, reserving it.
+fn read_id<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u64, D::Error> {
+    let id = u64::deserialize(deserializer)?;
+    reserve_id(id);
+    Ok(id)
 }
 
 // Join two sibling chains into one forest.
@@ -51,15 +110,133 @@ fn join_siblings(
             child: curr.child,
             next: right,
             label: curr.label,
+            id: curr.id,
+            folded: curr.folded,
         };
         right = Some(Rc::new(node));
     }
     right
 }
 
+// Build a sibling chain from a sequence of trees, followed by `tail`.
+fn build_siblings(
+    trees: Vec<Tree>,
+    tail: Option<Rc<Node>>,
+) -> Option<Rc<Node>> {
+    trees.into_iter().rev().fold(tail, |next, tree| {
+        let node = Node {
+            child: build_siblings(tree.children, None),
+            next,
+            label: Rc::from(tree.label),
+            id: new_id(),
+            folded: false,
+        };
+        Some(Rc::new(node))
+    })
+}
+
+// Convert a sibling chain into owned trees.
+fn to_trees(mut maybe_node: Option<&Node>) -> Vec<Tree> {
+    let mut trees = Vec::new();
+    while let Some(node) = maybe_node {
+        trees.push(Tree {
+            label: node.label.to_string(),
+            children: to_trees(node.child.as_deref()),
+        });
+        maybe_node = node.next.as_deref();
+    }
+    trees
+}
+
+// Set the fold state of every node in the sibling chain and its subtrees.
+fn fold_siblings(
+    mut maybe_node: Option<Rc<Node>>,
+    folded: bool,
+) -> Option<Rc<Node>> {
+    let mut nodes = Vec::new();
+    while let Some(node_rc) = maybe_node {
+        let node = Rc::unwrap_or_clone(node_rc);
+        maybe_node = node.next.clone();
+        nodes.push(node);
+    }
+    nodes.into_iter().rev().fold(None, |next, node| {
+        let node = Node {
+            folded: folded && node.child.is_some(),
+            child: fold_siblings(node.child, folded),
+            next,
+            label: node.label,
+            id: node.id,
+        };
+        Some(Rc::new(node))
+    })
+}
+
+// Count the nodes in a reversed sibling chain.
+fn count_prev(prev: Option<&RevNode>) -> usize {
+    std::iter::successors(prev, |rev_node| rev_node.prev.as_deref()).count()
+}
+
+// Call `visit` with the index path, label and id of each node in the sibling
+// chain's subtrees in pre-order, numbering the first sibling `index`, until
+// it breaks.
+fn walk_labels(
+    mut maybe_node: Option<&Node>,
+    mut index: usize,
+    path: &mut Vec<usize>,
+    visit: &mut impl FnMut(&[usize], &str, u64) -> ControlFlow<()>,
+) -> ControlFlow<()> {
+    while let Some(node) = maybe_node {
+        path.push(index);
+        visit(path, &node.label, node.id)?;
+        walk_labels(node.child.as_deref(), 0, path, visit)?;
+        path.pop();
+        maybe_node = node.next.as_deref();
+        index += 1;
+    }
+    ControlFlow::Continue(())
+}
+
+// Store a copy of `path` in `slot`, reusing its allocation.
+fn keep_path(slot: &mut Option<Vec<usize>>, path: &[usize]) {
+    let kept = slot.get_or_insert_with(Vec::new);
+    kept.clear();
+    kept.extend_from_slice(path);
+}
+
+// Return the index path of the node with the `id` in the sibling chain's
+// subtrees, numbering the first sibling `index`.
+fn find_id_path(
+    mut maybe_node: Option<&Node>,
+    mut index: usize,
+    path: &mut Vec<usize>,
+    id: u64,
+) -> Option<Vec<usize>> {
+    while let Some(node) = maybe_node {
+        path.push(index);
+        if node.id == id {
+            return Some(path.clone());
+        }
+        if let Some(found) = find_id_path(node.child.as_deref(), 0, path, id) {
+            return Some(found);
+        }
+        path.pop();
+        maybe_node = node.next.as_deref();
+        index += 1;
+    }
+    None
+}
+
 // Reverse the direction of the node’s sibling chain.
-fn reverse_siblings(mut node: Option<Rc<Node>>) -> Option<Rc<RevNode>> {
-    let mut reversed = None;
+fn reverse_siblings(node: Option<Rc<Node>>) -> Option<Rc<RevNode>> {
+    reverse_onto(node, None)
+}
+
+// Reverse the direction of the node’s sibling chain, continuing it with the
+// reversed chain `reversed`.
+fn reverse_onto(
+    mut node: Option<Rc<Node>>,
+    mut reversed: Option<Rc<RevNode>>,
+) -> Option<Rc<RevNode>> {
     while let Some(curr_rc) = node {
         let curr = Rc::unwrap_or_clone(curr_rc);
         node = curr.next;
@@ -67,6 +244,8 @@ fn reverse_siblings(mut node: Option<Rc<Node>>) -> Option<Rc<RevNode>> {
             child: curr.child,
             prev: reversed,
             label: curr.label,
+            id: curr.id,
+            folded: curr.folded,
         };
         reversed = Some(Rc::new(rev_node));
     }
@@ -82,9 +261,27 @@ impl FocusNode {
             prev: None,
             next: None,
             label: Rc::from(""),
+            id: new_id(),
+            folded: false,
         }
     }
 
+    /// Construct a forest from `trees`, focused on the first root.
+    pub fn from_trees(trees: Vec<Tree>) -> Option<Self> {
+        let root = build_siblings(trees, None)?;
+        let root = Rc::unwrap_or_clone(root);
+        let focus = Self {
+            parent: None,
+            child: root.child,
+            prev: None,
+            next: root.next,
+            label: root.label,
+            id: root.id,
+            folded: false,
+        };
+        Some(focus)
+    }
+
     /// Focus on the parent of the current focused node (if present).
     pub fn focus_parent(self) -> Self {
         match self.parent {
@@ -94,6 +291,8 @@ impl FocusNode {
                     child: self.child,
                     next: self.next,
                     label: self.label,
+                    id: self.id,
+                    folded: self.folded,
                 };
                 Self {
                     parent: parent.parent,
@@ -101,6 +300,8 @@ impl FocusNode {
                     prev: parent.prev,
                     next: parent.next,
                     label: parent.label,
+                    id: parent.id,
+                    folded: false,
                 }
             }
             None => self,
@@ -117,6 +318,7 @@ impl FocusNode {
                     prev: self.prev,
                     next: self.next,
                     label: self.label,
+                    id: self.id,
                 };
                 Self {
                     parent: Some(Rc::new(parent)),
@@ -124,6 +326,8 @@ impl FocusNode {
                     prev: None,
                     next: child.next,
                     label: child.label,
+                    id: child.id,
+                    folded: child.folded,
                 }
             }
             None => self,
@@ -139,6 +343,8 @@ impl FocusNode {
                     child: self.child,
                     next: self.next,
                     label: self.label,
+                    id: self.id,
+                    folded: self.folded,
                 };
                 Self {
                     parent: self.parent,
@@ -146,6 +352,8 @@ impl FocusNode {
                     prev: prev.prev,
                     next: Some(Rc::new(next)),
                     label: prev.label,
+                    id: prev.id,
+                    folded: prev.folded,
                 }
             }
             None => self,
@@ -161,6 +369,8 @@ impl FocusNode {
                     child: self.child,
                     prev: self.prev,
                     label: self.label,
+                    id: self.id,
+                    folded: self.folded,
                 };
                 Self {
                     parent: self.parent,
@@ -168,6 +378,8 @@ impl FocusNode {
                     prev: Some(Rc::new(prev)),
                     next: next.next,
                     label: next.label,
+                    id: next.id,
+                    folded: next.folded,
                 }
             }
             None => self,
@@ -183,6 +395,8 @@ impl FocusNode {
                     child: join_siblings(self.prev, self.next),
                     next: parent.next,
                     label: parent.label,
+                    id: parent.id,
+                    folded: false,
                 };
                 Self {
                     parent: parent.parent,
@@ -205,6 +419,7 @@ impl FocusNode {
                     prev: self.prev,
                     next: next.next,
                     label: next.label,
+                    id: next.id,
                 };
                 Self {
                     parent: Some(Rc::new(parent)),
@@ -226,6 +441,8 @@ impl FocusNode {
                     child: prev.child,
                     next: self.next,
                     label: prev.label,
+                    id: prev.id,
+                    folded: prev.folded,
                 };
                 Self {
                     prev: prev.prev,
@@ -246,6 +463,8 @@ impl FocusNode {
                     child: next.child,
                     prev: self.prev,
                     label: next.label,
+                    id: next.id,
+                    folded: next.folded,
                 };
                 Self {
                     prev: Some(Rc::new(prev)),
@@ -265,6 +484,7 @@ impl FocusNode {
             child: join_siblings(self.prev, child_plus_next),
             prev: None,
             next: None,
+            folded: false,
             ..self
         }
     }
@@ -286,10 +506,14 @@ impl FocusNode {
             child: self.child,
             next: None,
             label: self.label,
+            id: self.id,
+            folded: self.folded,
         };
         Self {
             child: Some(Rc::new(child)),
             label: Rc::from(""),
+            id: new_id(),
+            folded: false,
             ..self
         }
     }
@@ -301,6 +525,7 @@ impl FocusNode {
             prev: self.prev,
             next: self.next,
             label: self.label,
+            id: self.id,
         };
         Self {
             parent: Some(Rc::new(parent)),
@@ -308,6 +533,8 @@ impl FocusNode {
             prev: None,
             next: None,
             label: Rc::from(""),
+            id: new_id(),
+            folded: false,
         }
     }
 
@@ -317,11 +544,15 @@ impl FocusNode {
             child: self.child,
             next: self.next,
             label: self.label,
+            id: self.id,
+            folded: self.folded,
         };
         Self {
             child: None,
             next: Some(Rc::new(next)),
             label: Rc::from(""),
+            id: new_id(),
+            folded: false,
             ..self
         }
     }
@@ -332,11 +563,15 @@ impl FocusNode {
             child: self.child,
             prev: self.prev,
             label: self.label,
+            id: self.id,
+            folded: self.folded,
         };
         Self {
             child: None,
             prev: Some(Rc::new(prev)),
             label: Rc::from(""),
+            id: new_id(),
+            folded: false,
             ..self
         }
     }
@@ -353,28 +588,453 @@ impl FocusNode {
         focus
     }
 
-    /// Delete the focused node.
+    /// Focus on the last node of the forest shown in pre-order, skipping the
+    /// children of folded nodes.
+    pub fn focus_last_visible(self) -> Self {
+        self.focus_first_root()
+            .focus_last_sibling()
+            .focus_last_descendant()
+    }
+
+    // Focus on the last node shown in pre-order within the focused subtree.
+    fn focus_last_descendant(self) -> Self {
+        let mut focus = self;
+        while focus.child.is_some() && !focus.folded {
+            focus = focus.focus_child().focus_last_sibling();
+        }
+        focus
+    }
+
+    // Iterate over the ancestors of the focused node, starting from its
+    // parent.
+    fn ancestors(&self) -> impl Iterator<Item = &PathNode> {
+        std::iter::successors(self.parent.as_deref(), |path_node| {
+            path_node.parent.as_deref()
+        })
+    }
+
+    // Focus on the next sibling of the nearest of the focused node and its
+    // ancestors to have one.
+    fn focus_next_in_ancestors(self) -> Self {
+        let has_next = self.next.is_some()
+            || self.ancestors().any(|path_node| path_node.next.is_some());
+        if !has_next {
+            return self;
+        }
+        let mut focus = self;
+        while focus.next.is_none() {
+            focus = focus.focus_parent();
+        }
+        focus.focus_next()
+    }
+
+    /// Focus on the next node shown in pre-order, across levels of the tree.
+    pub fn focus_next_visible(self) -> Self {
+        if self.child.is_some() && !self.folded {
+            self.focus_child()
+        } else {
+            self.focus_next_in_ancestors()
+        }
+    }
+
+    /// Focus on the previous node shown in pre-order, across levels of the
+    /// tree.
+    pub fn focus_prev_visible(self) -> Self {
+        if self.prev.is_some() {
+            self.focus_prev().focus_last_descendant()
+        } else {
+            self.focus_parent()
+        }
+    }
+
+    /// Focus on the next sibling of the focused node's parent, or of the
+    /// nearest ancestor that has one.
+    pub fn focus_parent_next(self) -> Self {
+        if self.ancestors().any(|path_node| path_node.next.is_some()) {
+            self.focus_parent().focus_next_in_ancestors()
+        } else {
+            self
+        }
+    }
+
+    /// Return the sibling indices of the focused node and its ancestors,
+    /// starting from the root.
+    pub fn path(&self) -> Vec<usize> {
+        let mut path = vec![count_prev(self.prev.as_deref())];
+        path.extend(
+            self.ancestors()
+                .map(|path_node| count_prev(path_node.prev.as_deref())),
+        );
+        path.reverse();
+        path
+    }
+
+    /// Focus on the node at the index `path`, stopping early if it ends.
+    pub fn focus_path(self, path: &[usize]) -> Self {
+        let mut focus = self.focus_first_root();
+        for (depth, &index) in path.iter().enumerate() {
+            if depth > 0 {
+                if focus.child.is_none() {
+                    break;
+                }
+                focus = focus.focus_child();
+            }
+            for _ in 0..index {
+                focus = focus.focus_next();
+            }
+        }
+        focus
+    }
+
+    /// Return the path of the nearest node whose label satisfies `matches`,
+    /// searching forward or backward in pre-order and wrapping around.
+    pub fn find_label<P>(&self, matches: P, forward: bool) -> Option<Vec<usize>>
+    where
+        P: Fn(&str) -> bool,
+    {
+        // The nearest matches before and after the focus in pre-order.
+        let mut before = None;
+        let mut after = None;
+        let mut past_focus = false;
+        let mut visit = |path: &[usize], label: &str, id: u64| {
+            if id == self.id {
+                past_focus = true;
+            } else if matches(label) {
+                match (forward, past_focus) {
+                    (true, true) => {
+                        after = Some(path.to_vec());
+                        return ControlFlow::Break(());
+                    }
+                    (true, false) if before.is_none() => {
+                        before = Some(path.to_vec());
+                    }
+                    (true, false) => {}
+                    (false, false) => keep_path(&mut before, path),
+                    (false, true) => keep_path(&mut after, path),
+                }
+            }
+            ControlFlow::Continue(())
+        };
+        let root = self.clone().focus_first_root();
+        let mut path = vec![0];
+        if visit(&path, &root.label, root.id).is_continue()
+            && walk_labels(root.child.as_deref(), 0, &mut path, &mut visit)
+                .is_continue()
+        {
+            path.clear();
+            let _ = walk_labels(root.next.as_deref(), 1, &mut path, &mut visit);
+        }
+        let nearest = match forward {
+            true => after.or(before),
+            false => before.or(after),
+        };
+        nearest.or_else(|| matches(&self.label).then(|| self.path()))
+    }
+
+    /// Return the number of siblings following the focused node.
+    pub fn count_next(&self) -> usize {
+        std::iter::successors(self.next.as_deref(), |node| node.next.as_deref())
+            .count()
+    }
+
+    /// Return the identifier of the focused node, kept through edits.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Return the number of ancestors of the focused node.
+    pub fn depth(&self) -> usize {
+        self.ancestors().count()
+    }
+
+    /// Return the number of siblings before the focused node.
+    pub fn sibling_index(&self) -> usize {
+        count_prev(self.prev.as_deref())
+    }
+
+    /// Return the labels of the focused node's ancestors, starting from the
+    /// root.
+    pub fn ancestor_labels(&self) -> Vec<&str> {
+        let mut labels: Vec<&str> = self
+            .ancestors()
+            .map(|path_node| &*path_node.label)
+            .collect();
+        labels.reverse();
+        labels
+    }
+
+    /// Return the depth of the focused node or the ancestor with the `id`,
+    /// if there is one.
+    pub fn ancestor_depth(&self, id: u64) -> Option<usize> {
+        let depth = self.depth();
+        if self.id == id {
+            return Some(depth);
+        }
+        self.ancestors()
+            .position(|path_node| path_node.id == id)
+            .map(|index| depth - 1 - index)
+    }
+
+    /// Detach the subtree of the focused node's ancestor at `depth`, or of
+    /// the focused node if it is not as deep, as a forest of its own.
+    pub fn hoist(self, depth: usize) -> (Self, Hoist) {
+        let levels = self.depth().saturating_sub(depth);
+        if levels == 0 {
+            let hoist = Hoist {
+                parent: self.parent,
+                prev: self.prev,
+                next: self.next,
+                id: self.id,
+            };
+            let focus = Self {
+                parent: None,
+                prev: None,
+                next: None,
+                ..self
+            };
+            return (focus, hoist);
+        }
+        let mut path_nodes = Vec::with_capacity(levels);
+        let mut maybe_parent = self.parent;
+        for _ in 0..levels {
+            let parent_rc = maybe_parent.expect("Ancestor above the depth");
+            let mut path_node = Rc::unwrap_or_clone(parent_rc);
+            maybe_parent = path_node.parent.take();
+            path_nodes.push(path_node);
+        }
+        let top = path_nodes.last_mut().expect("Levels are not empty");
+        let hoist = Hoist {
+            parent: maybe_parent,
+            prev: top.prev.take(),
+            next: top.next.take(),
+            id: top.id,
+        };
+        let parent = path_nodes.into_iter().rev().fold(None, |parent, node| {
+            Some(Rc::new(PathNode { parent, ..node }))
+        });
+        let focus = Self {
+            parent,
+            child: self.child,
+            prev: self.prev,
+            next: self.next,
+            label: self.label,
+            id: self.id,
+            folded: self.folded,
+        };
+        (focus, hoist)
+    }
+
+    /// Return the path of the node with the `id`, if it is in the forest.
+    pub fn find_id(&self, id: u64) -> Option<Vec<usize>> {
+        let root = self.clone().focus_first_root();
+        if root.id == id {
+            return Some(vec![0]);
+        }
+        let mut path = vec![0];
+        find_id_path(root.child.as_deref(), 0, &mut path, id).or_else(|| {
+            path.clear();
+            find_id_path(root.next.as_deref(), 1, &mut path, id)
+        })
+    }
+
+    /// Return the whole forest as owned trees.
+    pub fn to_trees(&self) -> Vec<Tree> {
+        let root = self.clone().focus_first_root();
+        let mut trees = vec![Tree {
+            label: root.label.to_string(),
+            children: to_trees(root.child.as_deref()),
+        }];
+        trees.extend(to_trees(root.next.as_deref()));
+        trees
+    }
+
+    /// Set whether every node is folded, keeping the focused node visible.
+    pub fn fold_all(self, folded: bool) -> Self {
+        let path = self.path();
+        let root = self.focus_first_root();
+        let root = Self {
+            folded: folded && root.child.is_some(),
+            child: fold_siblings(root.child, folded),
+            next: fold_siblings(root.next, folded),
+            ..root
+        };
+        root.focus_path(&path)
+    }
+
+    /// Sort the focused node and its siblings by label, ignoring case.
+    pub fn sort_siblings(self) -> Self {
+        let mut siblings = Vec::new();
+        let mut maybe_prev = self.prev;
+        while let Some(prev_rc) = maybe_prev {
+            let prev = Rc::unwrap_or_clone(prev_rc);
+            maybe_prev = prev.prev;
+            siblings.push((prev.child, prev.label, prev.id, prev.folded));
+        }
+        siblings.reverse();
+        let focus_index = siblings.len();
+        siblings.push((self.child, self.label, self.id, self.folded));
+        let mut maybe_next = self.next;
+        while let Some(next_rc) = maybe_next {
+            let next = Rc::unwrap_or_clone(next_rc);
+            maybe_next = next.next;
+            siblings.push((next.child, next.label, next.id, next.folded));
+        }
+        let mut tagged: Vec<_> = siblings.into_iter().enumerate().collect();
+        tagged.sort_by_cached_key(|(_, (_, label, ..))| label.to_lowercase());
+        let position = tagged
+            .iter()
+            .position(|&(index, _)| index == focus_index)
+            .expect("Focused node missing from siblings");
+        let after = tagged.split_off(position + 1);
+        let (_, (child, label, id, folded)) = tagged.pop().unwrap();
+        let prev = tagged.into_iter().fold(None, |prev, (_, sibling)| {
+            let (child, label, id, folded) = sibling;
+            let rev_node = RevNode {
+                child,
+                prev,
+                label,
+                id,
+                folded,
+            };
+            Some(Rc::new(rev_node))
+        });
+        let next = after.into_iter().rev().fold(None, |next, (_, sibling)| {
+            let (child, label, id, folded) = sibling;
+            let node = Node {
+                child,
+                next,
+                label,
+                id,
+                folded,
+            };
+            Some(Rc::new(node))
+        });
+        Self {
+            parent: self.parent,
+            child,
+            prev,
+            next,
+            label,
+            id,
+            folded,
+        }
+    }
+
+    /// Toggle whether the focused node's children are hidden.
+    pub fn toggle_fold(self) -> Self {
+        Self {
+            folded: self.child.is_some() && !self.folded,
+            ..self
+        }
+    }
+
+    /// Focus on the first sibling of the current focused node.
+    pub fn focus_first_sibling(self) -> Self {
+        let mut focus = self;
+        while focus.prev.is_some() {
+            focus = focus.focus_prev();
+        }
+        focus
+    }
+
+    /// Focus on the last sibling of the current focused node.
+    pub fn focus_last_sibling(self) -> Self {
+        let mut focus = self;
+        while focus.next.is_some() {
+            focus = focus.focus_next();
+        }
+        focus
+    }
+
+    // Focus on the first sibling labeled `label`, appending it if missing.
+    fn focus_sibling_or_append(self, label: &str) -> Self {
+        let mut focus = self.focus_first_sibling();
+        while *focus.label != *label {
+            if focus.next.is_none() {
+                return focus.insert_next().set_label(label.to_string());
+            }
+            focus = focus.focus_next();
+        }
+        focus
+    }
+
+    // Insert a new node labeled `label` after the focused node's children.
+    fn append_child(self, label: String) -> Self {
+        match self.child {
+            Some(_) => self.focus_child().focus_last_sibling().insert_next(),
+            None => self.insert_child(),
+        }
+        .set_label(label)
+    }
+
+    /// Append a node labeled `label` under the node at the label `path`.
+    ///
+    /// Missing nodes along the path are created, and the resulting forest is
+    /// focused on its first root.
+    pub fn append_at_path(
+        maybe_focus: Option<Self>,
+        path: &[String],
+        label: String,
+    ) -> Self {
+        let focus = match (maybe_focus, path.split_first()) {
+            (None, None) => Self::new().set_label(label),
+            (Some(focus), None) => focus
+                .focus_first_root()
+                .focus_last_sibling()
+                .insert_next()
+                .set_label(label),
+            (maybe_focus, Some((first, rest))) => {
+                let mut focus = match maybe_focus {
+                    Some(focus) => {
+                        focus.focus_first_root().focus_sibling_or_append(first)
+                    }
+                    None => Self::new().set_label(first.clone()),
+                };
+                for name in rest {
+                    focus = match focus.child {
+                        Some(_) => {
+                            focus.focus_child().focus_sibling_or_append(name)
+                        }
+                        None => focus.insert_child().set_label(name.clone()),
+                    };
+                }
+                focus.append_child(label)
+            }
+        };
+        focus.focus_first_root()
+    }
+
+    /// Delete the focused node, keeping its children in its place.
     pub fn delete(self) -> Option<Self> {
-        let focus = self.flatten();
-        let new_focus = if let Some(next_rc) = focus.next {
+        self.flatten().delete_subtree()
+    }
+
+    /// Delete the focused node's subtree, focusing on the next sibling, the
+    /// previous sibling or the parent.
+    pub fn delete_subtree(self) -> Option<Self> {
+        let new_focus = if let Some(next_rc) = self.next {
             let next = Rc::unwrap_or_clone(next_rc);
             Self {
-                parent: focus.parent,
+                parent: self.parent,
                 child: next.child,
-                prev: focus.prev,
+                prev: self.prev,
                 next: next.next,
                 label: next.label,
+                id: next.id,
+                folded: next.folded,
             }
-        } else if let Some(prev_rc) = focus.prev {
+        } else if let Some(prev_rc) = self.prev {
             let prev = Rc::unwrap_or_clone(prev_rc);
             Self {
-                parent: focus.parent,
+                parent: self.parent,
                 child: prev.child,
                 prev: prev.prev,
                 next: None,
                 label: prev.label,
+                id: prev.id,
+                folded: prev.folded,
             }
-        } else if let Some(parent_rc) = focus.parent {
+        } else if let Some(parent_rc) = self.parent {
             let parent = Rc::unwrap_or_clone(parent_rc);
             Self {
                 parent: parent.parent,
@@ -382,6 +1042,8 @@ impl FocusNode {
                 prev: parent.prev,
                 next: parent.next,
                 label: parent.label,
+                id: parent.id,
+                folded: false,
             }
         } else {
             return None;
@@ -389,6 +1051,87 @@ impl FocusNode {
         Some(new_focus)
     }
 
+    /// Move the focused node and its `len - 1` next siblings under a new
+    /// parent with an empty label, focusing on the parent.
+    pub fn wrap_range(self, len: usize) -> Self {
+        let mut range = Vec::new();
+        let mut maybe_next = self.next;
+        for _ in 1..len {
+            let Some(next_rc) = maybe_next else {
+                break;
+            };
+            let next = Rc::unwrap_or_clone(next_rc);
+            maybe_next = next.next.clone();
+            range.push(next);
+        }
+        let tail = range
+            .into_iter()
+            .rev()
+            .fold(None, |next, node| Some(Rc::new(Node { next, ..node })));
+        let first = Node {
+            child: self.child,
+            next: tail,
+            label: self.label,
+            id: self.id,
+            folded: self.folded,
+        };
+        Self {
+            parent: self.parent,
+            child: Some(Rc::new(first)),
+            prev: self.prev,
+            next: maybe_next,
+            label: Rc::from(""),
+            id: new_id(),
+            folded: false,
+        }
+    }
+
+    /// Replace the focused node with its children, focusing on the first.
+    pub fn dissolve(self) -> Self {
+        if self.child.is_none() {
+            return self;
+        }
+        self.flatten()
+            .delete_subtree()
+            .expect("Flattened children follow the node")
+    }
+
+    /// Return the focused subtree and its `len - 1` next siblings as owned
+    /// trees.
+    pub fn range_to_trees(&self, len: usize) -> Vec<Tree> {
+        let mut trees = vec![Tree {
+            label: self.label.to_string(),
+            children: to_trees(self.child.as_deref()),
+        }];
+        let siblings = std::iter::successors(self.next.as_deref(), |node| {
+            node.next.as_deref()
+        });
+        trees.extend(siblings.take(len.saturating_sub(1)).map(|node| Tree {
+            label: node.label.to_string(),
+            children: to_trees(node.child.as_deref()),
+        }));
+        trees
+    }
+
+    /// Replace the focused subtree with the `trees`, focusing on the first.
+    pub fn replace_with_trees(self, trees: Vec<Tree>) -> Self {
+        if trees.is_empty() {
+            return self;
+        }
+        let first =
+            build_siblings(trees, self.next).expect("Trees are not empty");
+        let first = Rc::unwrap_or_clone(first);
+        Self {
+            parent: self.parent,
+            child: first.child,
+            prev: self.prev,
+            next: first.next,
+            label: first.label,
+            id: first.id,
+            folded: false,
+        }
+    }
+
     pub fn set_label(self, label: String) -> Self {
         Self {
             label: Rc::from(label),
@@ -400,3 +1143,98 @@ impl FocusNode {
         self.label.to_string()
     }
 }
+
+impl Hoist {
+    /// Put a hoisted forest back in place of the hoisted node, focused on the
+    /// same node, or on the nearest relative of the hoisted node if the
+    /// forest is empty.
+    pub fn attach(self, maybe_focus: Option<FocusNode>) -> Option<FocusNode> {
+        let Some(mut focus) = maybe_focus else {
+            let gap = FocusNode {
+                parent: self.parent,
+                child: None,
+                prev: self.prev,
+                next: self.next,
+                label: Rc::from(""),
+                id: self.id,
+                folded: false,
+            };
+            return gap.delete_subtree();
+        };
+        let mut path_nodes = Vec::new();
+        let mut maybe_parent = focus.parent;
+        while let Some(parent_rc) = maybe_parent {
+            let mut path_node = Rc::unwrap_or_clone(parent_rc);
+            maybe_parent = path_node.parent.take();
+            path_nodes.push(path_node);
+        }
+        let (prev, next) = match path_nodes.last_mut() {
+            Some(top) => (&mut top.prev, &mut top.next),
+            None => (&mut focus.prev, &mut focus.next),
+        };
+        *prev = reverse_onto(join_siblings(prev.take(), None), self.prev);
+        *next = join_siblings(reverse_siblings(next.take()), self.next);
+        let parent = path_nodes
+            .into_iter()
+            .rev()
+            .fold(self.parent, |parent, node| {
+                Some(Rc::new(PathNode { parent, ..node }))
+            });
+        Some(FocusNode { parent, ..focus })
+    }
+
+    /// Return the identifier of the hoisted node.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    // Iterate over the ancestors of the hoisted node, starting from its
+    // parent.
+    fn ancestors(&self) -> impl Iterator<Item = &PathNode> {
+        std::iter::successors(self.parent.as_deref(), |path_node| {
+            path_node.parent.as_deref()
+        })
+    }
+
+    /// Return the number of ancestors of the hoisted node.
+    pub fn depth(&self) -> usize {
+        self.ancestors().count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::sample_forest as forest;
+
+    #[test]
+    fn find_label_forward() {
+        let is_b = |label: &str| label.starts_with('b');
+        assert_eq!(forest(&[0]).find_label(is_b, true), Some(vec![1]));
+        assert_eq!(forest(&[1]).find_label(is_b, true), Some(vec![1, 0]));
+        assert_eq!(forest(&[1, 0]).find_label(is_b, true), Some(vec![1]));
+        assert_eq!(forest(&[2]).find_label(is_b, true), Some(vec![1]));
+    }
+
+    #[test]
+    fn find_label_backward() {
+        let is_a = |label: &str| label.starts_with('a');
+        assert_eq!(forest(&[0, 1]).find_label(is_a, false), Some(vec![0, 0]));
+        assert_eq!(forest(&[0]).find_label(is_a, false), Some(vec![0, 1, 0]));
+        assert_eq!(forest(&[2]).find_label(is_a, false), Some(vec![0, 1, 0]));
+    }
+
+    #[test]
+    fn find_label_only_focus_or_none() {
+        let is_c = |label: &str| label == "c";
+        assert_eq!(forest(&[2]).find_label(is_c, true), Some(vec![2]));
+        assert_eq!(forest(&[2]).find_label(is_c, false), Some(vec![2]));
+        assert_eq!(forest(&[0]).find_label(|label| label == "z", true), None);
+    }
+
+    #[test]
+    fn find_label_includes_folded() {
+        let focus = forest(&[1]).toggle_fold().focus_path(&[0]);
+        let is_b1 = |label: &str| label == "b1";
+        assert_eq!(focus.find_label(is_b1, true), Some(vec![1, 0]));
+    }
+}
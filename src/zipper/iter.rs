@@ -14,6 +14,7 @@ pub struct NodeInfo<'a> {
     pub position: NodePosition,
     pub is_last_sibling: bool,
     pub is_focused: bool,
+    pub is_folded: bool,
 }
 
 // A stack frame used during pre-order traversal of a Node.
@@ -46,7 +47,8 @@ impl<'a> Iterator for NodePreOrderIter<'a> {
         } else {
             true
         };
-        if let Some(child) = &node.child {
+        let is_folded = node.folded && node.child.is_some();
+        if let Some(child) = node.child.as_ref().filter(|_| !node.folded) {
             let child_frame = Frame {
                 node: child,
                 position: NodePosition::FirstChild,
@@ -58,6 +60,7 @@ impl<'a> Iterator for NodePreOrderIter<'a> {
             position,
             is_last_sibling,
             is_focused: false,
+            is_folded,
         };
         Some(node_info)
     }
@@ -94,14 +97,16 @@ fn rev_node_iter(
         } else {
             NodePosition::SubsequentChild
         };
+        let visible_child =
+            rev_node.child.as_deref().filter(|_| !rev_node.folded);
         let info = NodeInfo {
             label: &rev_node.label,
             position,
             is_last_sibling: false,
             is_focused: false,
+            is_folded: rev_node.folded && rev_node.child.is_some(),
         };
-        let child_iter =
-            node_iter(rev_node.child.as_deref(), NodePosition::FirstChild);
+        let child_iter = node_iter(visible_child, NodePosition::FirstChild);
         std::iter::once(info).chain(child_iter)
     })
 }
@@ -121,11 +126,12 @@ fn siblings_iter(focus: &FocusNode) -> impl Iterator<Item = NodeInfo<'_>> {
         position,
         is_last_sibling: focus.next.is_none(),
         is_focused: true,
+        is_folded: focus.folded && focus.child.is_some(),
     };
+    let visible_child = focus.child.as_deref().filter(|_| !focus.folded);
     let prev_iter = rev_node_iter(focus.prev.as_deref(), is_root);
     let focus_iter = std::iter::once(focus_info);
-    let child_iter =
-        node_iter(focus.child.as_deref(), NodePosition::FirstChild);
+    let child_iter = node_iter(visible_child, NodePosition::FirstChild);
     let next_iter = node_iter(focus.next.as_deref(), next_pos);
     prev_iter
         .chain(focus_iter)
@@ -155,6 +161,7 @@ pub fn focus_iter(focus: &FocusNode) -> impl Iterator<Item = NodeInfo<'_>> {
             position,
             is_last_sibling: path_node.next.is_none(),
             is_focused: false,
+            is_folded: false,
         };
         let prev_iter = rev_node_iter(path_node.prev.as_deref(), is_root);
         let path_node_iter = std::iter::once(path_node_info);
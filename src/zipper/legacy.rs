@@ -0,0 +1,95 @@
+use std::rc::Rc;
+
+use serde::Deserialize;
+
+use crate::zipper::new_id;
+
+// A node as saved before nodes had identifiers.
+#[derive(Deserialize)]
+struct Node {
+    child: Option<Box<Node>>,
+    next: Option<Box<Node>>,
+    label: Rc<str>,
+}
+
+// A reversed sibling node as saved before nodes had identifiers.
+#[derive(Deserialize)]
+struct RevNode {
+    child: Option<Box<Node>>,
+    prev: Option<Box<RevNode>>,
+    label: Rc<str>,
+}
+
+// A path node as saved before nodes had identifiers.
+#[derive(Deserialize)]
+struct PathNode {
+    parent: Option<Box<PathNode>>,
+    prev: Option<Box<RevNode>>,
+    next: Option<Box<Node>>,
+    label: Rc<str>,
+}
+
+/// The focused node as saved before nodes had identifiers.
+#[derive(Deserialize)]
+pub struct FocusNode {
+    parent: Option<Box<PathNode>>,
+    child: Option<Box<Node>>,
+    prev: Option<Box<RevNode>>,
+    next: Option<Box<Node>>,
+    label: Rc<str>,
+}
+
+// Convert a saved link, giving each node a new identifier.
+fn convert<T, U: From<T>>(maybe_node: Option<Box<T>>) -> Option<Rc<U>> {
+    maybe_node.map(|node| Rc::new(U::from(*node)))
+}
+
+impl From<Node> for super::Node {
+    fn from(node: Node) -> Self {
+        Self {
+            child: convert(node.child),
+            next: convert(node.next),
+            label: node.label,
+            id: new_id(),
+            folded: false,
+        }
+    }
+}
+
+impl From<RevNode> for super::RevNode {
+    fn from(node: RevNode) -> Self {
+        Self {
+            child: convert(node.child),
+            prev: convert(node.prev),
+            label: node.label,
+            id: new_id(),
+            folded: false,
+        }
+    }
+}
+
+impl From<PathNode> for super::PathNode {
+    fn from(node: PathNode) -> Self {
+        Self {
+            parent: convert(node.parent),
+            prev: convert(node.prev),
+            next: convert(node.next),
+            label: node.label,
+            id: new_id(),
+        }
+    }
+}
+
+impl From<FocusNode> for super::FocusNode {
+    fn from(node: FocusNode) -> Self {
+        Self {
+            parent: convert(node.parent),
+            child: convert(node.child),
+            prev: convert(node.prev),
+            next: convert(node.next),
+            label: node.label,
+            id: new_id(),
+            folded: false,
+        }
+    }
+}
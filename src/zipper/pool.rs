@@ -0,0 +1,313 @@
+use std::{collections::HashMap, rc::Rc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::zipper::{FocusNode, Node, PathNode, RevNode, reserve_id};
+
+// A node whose links are the indices of nodes stored before it.
+#[derive(Serialize, Deserialize)]
+struct PoolNode {
+    child: Option<usize>,
+    next: Option<usize>,
+    label: usize,
+    id: u64,
+}
+
+// A reversed sibling node whose links are indices.
+#[derive(Serialize, Deserialize)]
+struct PoolRevNode {
+    child: Option<usize>,
+    prev: Option<usize>,
+    label: usize,
+    id: u64,
+}
+
+// A path node whose links are indices.
+#[derive(Serialize, Deserialize)]
+struct PoolPathNode {
+    parent: Option<usize>,
+    prev: Option<usize>,
+    next: Option<usize>,
+    label: usize,
+    id: u64,
+}
+
+// A focused node whose links are indices.
+#[derive(Serialize, Deserialize)]
+struct PoolFocus {
+    parent: Option<usize>,
+    child: Option<usize>,
+    prev: Option<usize>,
+    next: Option<usize>,
+    label: usize,
+    id: u64,
+}
+
+/// Forests stored so that the nodes and labels they share are written once.
+#[derive(Default, Serialize, Deserialize)]
+pub struct ForestPool {
+    labels: Vec<Rc<str>>,
+    nodes: Vec<PoolNode>,
+    rev_nodes: Vec<PoolRevNode>,
+    path_nodes: Vec<PoolPathNode>,
+    forests: Vec<Option<PoolFocus>>,
+}
+
+// Indices of the shared values already stored, keyed by their addresses,
+// which stay valid while the stored forests are borrowed.
+#[derive(Default)]
+struct Stored {
+    labels: HashMap<*const u8, usize>,
+    nodes: HashMap<*const Node, usize>,
+    rev_nodes: HashMap<*const RevNode, usize>,
+    path_nodes: HashMap<*const PathNode, usize>,
+}
+
+// Return the shared value at the index of a link, if any.
+fn link<T>(values: &[Rc<T>], index: Option<usize>) -> Option<Rc<T>> {
+    index.map(|index| Rc::clone(&values[index]))
+}
+
+impl ForestPool {
+    /// Store the `forests` in order, sharing their common nodes.
+    pub fn new<'a, I>(forests: I) -> Self
+    where
+        I: IntoIterator<Item = Option<&'a FocusNode>>,
+    {
+        let mut pool = Self::default();
+        let mut stored = Stored::default();
+        for maybe_focus in forests {
+            let forest =
+                maybe_focus.map(|focus| pool.store_focus(&mut stored, focus));
+            pool.forests.push(forest);
+        }
+        pool
+    }
+
+    fn store_label(&mut self, stored: &mut Stored, label: &Rc<str>) -> usize {
+        let address = Rc::as_ptr(label) as *const u8;
+        if let Some(&index) = stored.labels.get(&address) {
+            return index;
+        }
+        self.labels.push(Rc::clone(label));
+        stored.labels.insert(address, self.labels.len() - 1);
+        self.labels.len() - 1
+    }
+
+    fn store_node(&mut self, stored: &mut Stored, rc: &Rc<Node>) -> usize {
+        if let Some(&index) = stored.nodes.get(&Rc::as_ptr(rc)) {
+            return index;
+        }
+        let node = PoolNode {
+            child: rc
+                .child
+                .as_ref()
+                .map(|child| self.store_node(stored, child)),
+            next: rc.next.as_ref().map(|next| self.store_node(stored, next)),
+            label: self.store_label(stored, &rc.label),
+            id: rc.id,
+        };
+        self.nodes.push(node);
+        stored.nodes.insert(Rc::as_ptr(rc), self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    fn store_rev_node(
+        &mut self,
+        stored: &mut Stored,
+        rc: &Rc<RevNode>,
+    ) -> usize {
+        if let Some(&index) = stored.rev_nodes.get(&Rc::as_ptr(rc)) {
+            return index;
+        }
+        let node = PoolRevNode {
+            child: rc
+                .child
+                .as_ref()
+                .map(|child| self.store_node(stored, child)),
+            prev: rc
+                .prev
+                .as_ref()
+                .map(|prev| self.store_rev_node(stored, prev)),
+            label: self.store_label(stored, &rc.label),
+            id: rc.id,
+        };
+        self.rev_nodes.push(node);
+        stored
+            .rev_nodes
+            .insert(Rc::as_ptr(rc), self.rev_nodes.len() - 1);
+        self.rev_nodes.len() - 1
+    }
+
+    fn store_path_node(
+        &mut self,
+        stored: &mut Stored,
+        rc: &Rc<PathNode>,
+    ) -> usize {
+        if let Some(&index) = stored.path_nodes.get(&Rc::as_ptr(rc)) {
+            return index;
+        }
+        let node = PoolPathNode {
+            parent: rc
+                .parent
+                .as_ref()
+                .map(|parent| self.store_path_node(stored, parent)),
+            prev: rc
+                .prev
+                .as_ref()
+                .map(|prev| self.store_rev_node(stored, prev)),
+            next: rc.next.as_ref().map(|next| self.store_node(stored, next)),
+            label: self.store_label(stored, &rc.label),
+            id: rc.id,
+        };
+        self.path_nodes.push(node);
+        stored
+            .path_nodes
+            .insert(Rc::as_ptr(rc), self.path_nodes.len() - 1);
+        self.path_nodes.len() - 1
+    }
+
+    fn store_focus(
+        &mut self,
+        stored: &mut Stored,
+        focus: &FocusNode,
+    ) -> PoolFocus {
+        PoolFocus {
+            parent: focus
+                .parent
+                .as_ref()
+                .map(|parent| self.store_path_node(stored, parent)),
+            child: focus
+                .child
+                .as_ref()
+                .map(|child| self.store_node(stored, child)),
+            prev: focus
+                .prev
+                .as_ref()
+                .map(|prev| self.store_rev_node(stored, prev)),
+            next: focus
+                .next
+                .as_ref()
+                .map(|next| self.store_node(stored, next)),
+            label: self.store_label(stored, &focus.label),
+            id: focus.id,
+        }
+    }
+
+    /// Rebuild the stored forests in order, sharing their common nodes again.
+    pub fn into_forests(self) -> Vec<Option<FocusNode>> {
+        let labels = self.labels;
+        let mut nodes: Vec<Rc<Node>> = Vec::with_capacity(self.nodes.len());
+        for node in self.nodes {
+            reserve_id(node.id);
+            nodes.push(Rc::new(Node {
+                child: link(&nodes, node.child),
+                next: link(&nodes, node.next),
+                label: Rc::clone(&labels[node.label]),
+                id: node.id,
+                folded: false,
+            }));
+        }
+        let mut rev_nodes: Vec<Rc<RevNode>> =
+            Vec::with_capacity(self.rev_nodes.len());
+        for node in self.rev_nodes {
+            reserve_id(node.id);
+            rev_nodes.push(Rc::new(RevNode {
+                child: link(&nodes, node.child),
+                prev: link(&rev_nodes, node.prev),
+                label: Rc::clone(&labels[node.label]),
+                id: node.id,
+                folded: false,
+            }));
+        }
+        let mut path_nodes: Vec<Rc<PathNode>> =
+            Vec::with_capacity(self.path_nodes.len());
+        for node in self.path_nodes {
+            reserve_id(node.id);
+            path_nodes.push(Rc::new(PathNode {
+                parent: link(&path_nodes, node.parent),
+                prev: link(&rev_nodes, node.prev),
+                next: link(&nodes, node.next),
+                label: Rc::clone(&labels[node.label]),
+                id: node.id,
+            }));
+        }
+        self.forests
+            .into_iter()
+            .map(|maybe_focus| {
+                let focus = maybe_focus?;
+                reserve_id(focus.id);
+                Some(FocusNode {
+                    parent: link(&path_nodes, focus.parent),
+                    child: link(&nodes, focus.child),
+                    prev: link(&rev_nodes, focus.prev),
+                    next: link(&nodes, focus.next),
+                    label: Rc::clone(&labels[focus.label]),
+                    id: focus.id,
+                    folded: false,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::sample_forest;
+
+    // The sample forest focused on a2.
+    fn forest() -> FocusNode {
+        sample_forest(&[0, 1])
+    }
+
+    fn round_trip(pool: &ForestPool) -> Vec<Option<FocusNode>> {
+        let bytes = bincode::serialize(pool).unwrap();
+        let pool: ForestPool = bincode::deserialize(&bytes).unwrap();
+        pool.into_forests()
+    }
+
+    #[test]
+    fn shared_nodes_stored_once() {
+        let first = forest();
+        let second = first.clone().set_label(String::from("changed"));
+        let pool = ForestPool::new([Some(&first), Some(&second)]);
+        // a2x, b, b1 and c as nodes, a1 as a reversed sibling, a as a
+        // parent.
+        assert_eq!(pool.nodes.len(), 4);
+        assert_eq!(pool.rev_nodes.len(), 1);
+        assert_eq!(pool.path_nodes.len(), 1);
+        assert_eq!(pool.forests.len(), 2);
+        let forests = round_trip(&pool);
+        let (first, second) = match forests.as_slice() {
+            [Some(first), Some(second)] => (first, second),
+            _ => panic!("Expected two forests"),
+        };
+        let child = |focus: &FocusNode| focus.child.clone().unwrap();
+        assert!(Rc::ptr_eq(&child(first), &child(second)));
+        let parent = |focus: &FocusNode| focus.parent.clone().unwrap();
+        assert!(Rc::ptr_eq(&parent(first), &parent(second)));
+    }
+
+    #[test]
+    fn forests_survive_round_trip() {
+        let first = forest();
+        let second = first.clone().delete_subtree().unwrap();
+        let pool = ForestPool::new([Some(&first), None, Some(&second)]);
+        let forests = round_trip(&pool);
+        assert!(forests[0].as_ref() == Some(&first));
+        assert!(forests[1].is_none());
+        assert!(forests[2].as_ref() == Some(&second));
+        let restored = forests[0].as_ref().unwrap();
+        assert_eq!(restored.path(), vec![0, 1]);
+        assert_eq!(restored.id(), first.id());
+        let ids = |focus: &FocusNode| {
+            let root = focus.clone().focus_first_root();
+            ["a2x", "b1"].map(|label| {
+                let path = root.find_label(|l| l == label, true).unwrap();
+                root.clone().focus_path(&path).id()
+            })
+        };
+        assert_eq!(ids(restored), ids(&first));
+    }
+}